@@ -1,28 +1,357 @@
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
-use std::{cell, mem, result, thread, vec};
+use std::cmp::Reverse;
+use std::ops::{Add, Deref, Sub};
+use std::{cell, error, fmt, mem, result, thread, vec};
 use std::arch::asm;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::{self, DefaultHasher, Entry};
 use std::collections::linked_list::LinkedList;
 use std::hash::{Hash, Hasher};
+#[cfg(feature = "fast-hash")]
+use std::hash::BuildHasherDefault;
+#[cfg(not(feature = "fast-hash"))]
+use std::collections::hash_map::RandomState;
 use std::ptr::null_mut;
 use typed_arena::Arena;
+#[cfg(debug_assertions)]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "serde")]
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rand")]
+use rand::Rng;
 
 struct CovariantLifetime<'id>(PhantomData<&'id ()>);
 struct InvariantLifetime<'id>(PhantomData<*mut &'id ()>);
 struct ContravariantLifetime<'id>(PhantomData<fn(&'id ()) -> ()>);
 
+// token di un network. Come in GhostCell i nodi sono raggiungibili attraverso riferimenti condivisi (add prende &self),
+// quindi è il token a stabilire chi può leggere e chi può scrivere. Il valore di un nodo e i costi dei suoi archi si
+// leggono senza token solo attraverso il suo NodeRef; i valori, i costi e i NodeVisit degli altri nodi (visite,
+// iteratori e algoritmi sull'intero network) richiedono &GgToken e restano in prestito da esso, mentre ogni modifica di
+// un nodo (value_mut, link*, unlink*, set_weight) richiede &mut NodeRef e &mut GgToken. Così un riferimento ottenuto in
+// lettura non può sopravvivere a una scrittura sullo stesso nodo. Gli algoritmi che guardano solo quali archi esistono
+// (componenti, gradi, ordinamenti restituiti come NodeId) non leggono né valori né costi e non richiedono il token
 pub struct GgToken<'id> {
     _marker: InvariantLifetime<'id>,
 }
 
+// ogni network riceve una generazione diversa, salvata anche nei suoi nodi: un arco verso un nodo con un'altra
+// generazione porta in un altro network, i cui valori e costi sono protetti da un altro token, quindi visite e algoritmi
+// non lo seguono. In debug le generazioni dei network ancora vivi sono registrate in LIVE_GENERATIONS e gli archi verso
+// nodi di altri network ricordano la generazione del network di destinazione, così seguire un arco verso un network
+// già distrutto fa fallire un'asserzione invece di leggere memoria liberata
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+#[cfg(debug_assertions)]
+static LIVE_GENERATIONS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+fn register_generation() -> u64 {
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    #[cfg(debug_assertions)]
+    LIVE_GENERATIONS.lock().unwrap().push(generation);
+    generation
+}
+
+// hasher usato per le mappe degli archi. Di default è SipHash, con la feature fast-hash viene usato PointerHasher
+#[cfg(not(feature = "fast-hash"))]
+type LinkHasher = RandomState;
+#[cfg(feature = "fast-hash")]
+type LinkHasher = BuildHasherDefault<PointerHasher>;
+
+// hasher per gli indirizzi dei nodi. Le chiavi sono puntatori già unici quindi non serve la resistenza alle collisioni
+// di SipHash: basta distribuirne i bit con una moltiplicazione (come FxHash). La rotazione finale porta nei bit bassi
+// (usati per scegliere il bucket) i bit centrali del prodotto, perché quelli bassi degli indirizzi sono sempre zero
+#[cfg(feature = "fast-hash")]
+#[derive(Default)]
+pub struct PointerHasher {
+    hash: u64,
+}
+
+#[cfg(feature = "fast-hash")]
+impl Hasher for PointerHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ value).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash.rotate_left(26)
+    }
+}
+
 pub struct Node<T, G> {
-    links: HashMap<*mut Node<T, G>, G>,
+    links: HashMap<*mut Node<T, G>, G, LinkHasher>,
     value: T,
+    // indirizzo del nodo stesso, permette di restituire un &NodeVisit che vive quanto il nodo
+    this: *mut Node<T, G>,
+    // nodi di altri network con almeno un arco verso questo nodo (creato da link_inner, link_across o link_outer), a
+    // cui va segnalata la distruzione del nodo. Viene aggiornato dagli altri network anche mentre questo nodo è in
+    // prestito, quindi è in una UnsafeCell; i network collegati stanno tutti sullo stesso thread (GenerationalGraph
+    // non è Send) e i thread dei metodi par_* leggono soltanto, quindi le scritture non sono mai concorrenti
+    foreign_sources: cell::UnsafeCell<Vec<*mut Node<T, G>>>,
+    // destinazioni di archi verso nodi di altri network già distrutti. Quando un network viene distrutto le mappe
+    // degli archi dei nodi che puntano ai suoi nodi potrebbero essere in prestito, quindi gli archi restano in links
+    // ma vengono ignorati da letture e algoritmi, e scompaiono alla prima modifica che riguarda la stessa destinazione
+    dead_links: cell::UnsafeCell<Vec<*mut Node<T, G>>>,
+    // costi degli archi paralleli creati con link_multi oltre al primo, che resta in links. Una destinazione compare
+    // qui solo se compare anche in links
+    parallel: HashMap<*mut Node<T, G>, Vec<G>, LinkHasher>,
+    // nodo rimosso con mark_removed: non è più nell'indice del network ma la sua memoria resta nell'arena
+    removed: bool,
+    // posizione del nodo nell'ordine di creazione, restituita da NodeRef::index
+    serial: usize,
+    // generazione del network che contiene il nodo
+    generation: u64,
+    // generazione del network di destinazione di ogni arco verso un altro network
+    #[cfg(debug_assertions)]
+    foreign: HashMap<*mut Node<T, G>, u64, LinkHasher>,
+}
+
+impl<T, G> Node<T, G> {
+    // true se dest è stato distrutto insieme al suo network mentre questo nodo aveva ancora un arco verso di lui
+    fn is_dead(&self, dest: *mut Node<T, G>) -> bool {
+        unsafe { (*self.dead_links.get()).contains(&dest) }
+    }
+
+    // numero di destinazioni distinte degli archi uscenti ancora esistenti. Ogni arco morto ha la sua chiave in links
+    fn live_count(&self) -> usize {
+        self.links.len() - unsafe { (*self.dead_links.get()).len() }
+    }
+
+    // archi uscenti (solo il primo per destinazione) escluse le destinazioni già distrutte
+    fn live_links(&self) -> impl Iterator<Item = (&*mut Node<T, G>, &G)> {
+        self.links.iter().filter(move |(&dest, _)| !self.is_dead(dest))
+    }
+
+    // destinazioni di live_links
+    fn live_keys(&self) -> impl Iterator<Item = &*mut Node<T, G>> {
+        self.live_links().map(|(dest, _)| dest)
+    }
+
+    // archi paralleli come coppie (destinazione, costo) escluse le destinazioni già distrutte
+    fn live_parallel(&self) -> impl Iterator<Item = (&*mut Node<T, G>, &G)> {
+        self.parallel.iter()
+            .filter(move |(&dest, _)| !self.is_dead(dest))
+            .flat_map(|(dest, costs)| costs.iter().map(move |cost| (dest, cost)))
+    }
+
+    // tutti gli archi uscenti come coppie (destinazione, costo), i primi seguiti da quelli paralleli, escluse le
+    // destinazioni già distrutte
+    fn live_edges(&self) -> impl Iterator<Item = (&*mut Node<T, G>, &G)> {
+        self.live_links().chain(self.live_parallel())
+    }
+
+    // costi di tutti gli archi verso dest, il primo seguito da quelli paralleli. Vuoto se dest è già stato distrutto
+    fn costs_to(&self, dest: *mut Node<T, G>) -> impl Iterator<Item = &G> {
+        let parallel = if self.is_dead(dest) { None } else { self.parallel.get(&dest) };
+        self.link_to(dest).into_iter().chain(parallel.into_iter().flatten())
+    }
+
+    // costo minimo tra gli archi verso dest non negativi, cioè quelli che Dijkstra può percorrere
+    fn cheapest_to(&self, dest: *mut Node<T, G>) -> Option<&G>
+    where
+        G: Ord + Default,
+    {
+        self.costs_to(dest).filter(|&cost| *cost >= G::default()).min()
+    }
+
+    // costo del primo arco verso dest, None se l'arco non esiste o dest è già stato distrutto
+    fn link_to(&self, dest: *mut Node<T, G>) -> Option<&G> {
+        if self.is_dead(dest) { None } else { self.links.get(&dest) }
+    }
+
+    // come link_to, ma il costo può essere modificato
+    fn link_to_mut(&mut self, dest: *mut Node<T, G>) -> Option<&mut G> {
+        if self.is_dead(dest) { None } else { self.links.get_mut(&dest) }
+    }
+
+    // dimentica l'arco morto verso dest, se c'è. Un nuovo nodo può essere allocato allo stesso indirizzo di un nodo
+    // distrutto, quindi va chiamato prima di creare un arco verso dest per non confonderlo con uno vecchio
+    fn revive(&mut self, dest: *mut Node<T, G>) {
+        let dead = self.dead_links.get_mut();
+
+        if let Some(i) = dead.iter().position(|&other| other == dest) {
+            dead.swap_remove(i);
+            #[cfg(debug_assertions)]
+            self.foreign.remove(&dest);
+            self.parallel.remove(&dest);
+            self.links.remove(&dest);
+        }
+    }
+
+    // inserisce l'arco verso dest sostituendo il primo arco esistente, di cui ritorna il costo
+    fn insert_link(&mut self, dest: *mut Node<T, G>, cost: G) -> Option<G> {
+        self.revive(dest);
+        self.links.insert(dest, cost)
+    }
+
+    // aggiunge un arco verso dest senza toccare quelli esistenti: il primo finisce in links, i successivi in parallel
+    fn push_link(&mut self, dest: *mut Node<T, G>, cost: G) {
+        self.revive(dest);
+
+        match self.links.entry(dest) {
+            Entry::Occupied(_) => self.parallel.entry(dest).or_default().push(cost),
+            Entry::Vacant(entry) => {
+                entry.insert(cost);
+            }
+        }
+    }
+
+    // rimuove tutti gli archi verso dest, restituendo il costo del primo. Se dest è già stato distrutto l'arco non
+    // esisteva più e viene restituito None
+    fn remove_links(&mut self, dest: *mut Node<T, G>) -> Option<G> {
+        if self.is_dead(dest) {
+            self.revive(dest);
+            return None;
+        }
+
+        #[cfg(debug_assertions)]
+        self.foreign.remove(&dest);
+        self.parallel.remove(&dest);
+        let cost = self.links.remove(&dest);
+
+        if cost.is_some() {
+            unsafe { self.detach_from(dest); }
+        }
+
+        cost
+    }
+
+    // rimuove solo il primo arco verso dest: se esistono archi paralleli il primo di essi prende il suo posto in links
+    fn remove_first_link(&mut self, dest: *mut Node<T, G>) {
+        if self.is_dead(dest) {
+            self.revive(dest);
+            return;
+        }
+
+        match self.parallel.get_mut(&dest) {
+            Some(costs) => {
+                let first = costs.remove(0);
+                if costs.is_empty() {
+                    self.parallel.remove(&dest);
+                }
+                self.links.insert(dest, first);
+            }
+            None => {
+                self.remove_links(dest);
+            }
+        }
+    }
+
+    // arco verso un nodo di un altro network (o dello stesso, con link_across): se dest è in un altro network questo
+    // nodo viene aggiunto ai suoi foreign_sources, così la distruzione di dest segna l'arco come morto. In debug ne
+    // viene ricordata la generazione
+    fn insert_foreign(&mut self, dest: *mut Node<T, G>, cost: G) -> Option<G> {
+        self.revive(dest);
+
+        unsafe {
+            if (*dest).generation != self.generation {
+                let sources = &mut *(*dest).foreign_sources.get();
+                if !sources.contains(&self.this) {
+                    sources.push(self.this);
+                }
+
+                #[cfg(debug_assertions)]
+                self.foreign.insert(dest, (*dest).generation);
+            }
+        }
+
+        self.links.insert(dest, cost)
+    }
+
+    // toglie questo nodo dai foreign_sources di dest se dest appartiene a un altro network, da chiamare quando viene
+    // rimosso l'ultimo arco verso dest (che deve esistere ancora). Un cappio non va letto tramite dest: self può essere
+    // un prestito esclusivo dello stesso nodo
+    unsafe fn detach_from(&self, dest: *mut Node<T, G>) {
+        if dest != self.this && (*dest).generation != self.generation {
+            let sources = &mut *(*dest).foreign_sources.get();
+            if let Some(i) = sources.iter().position(|&source| source == self.this) {
+                sources.swap_remove(i);
+            }
+        }
+    }
+
+    // toglie questo nodo dai foreign_sources di tutte le destinazioni dei suoi archi ancora esistenti
+    unsafe fn detach_all(&self) {
+        for &dest in self.live_keys() {
+            self.detach_from(dest);
+        }
+    }
+
+    // rimuove tutti gli archi uscenti, compresi quelli verso altri network
+    unsafe fn clear_links(&mut self) {
+        self.detach_all();
+        self.dead_links.get_mut().clear();
+        #[cfg(debug_assertions)]
+        self.foreign.clear();
+        self.links.clear();
+        self.parallel.clear();
+    }
+
+    // separa il nodo dagli altri network prima che venga distrutto, qualunque sia l'ordine di distruzione dei network:
+    // i nodi con un arco verso questo nodo lo segnano tra i loro archi morti (senza toccare le loro mappe degli archi,
+    // che potrebbero essere in prestito) e le destinazioni dei suoi archi smettono di ricordarlo tra i foreign_sources
+    unsafe fn release(&mut self) {
+        for &source in self.foreign_sources.get_mut().iter() {
+            (*(*source).dead_links.get()).push(self.this);
+        }
+
+        self.foreign_sources.get_mut().clear();
+        self.detach_all();
+    }
+
+    // true se l'arco verso next va saltato perché next è stato distrutto insieme al suo network o rimosso con
+    // mark_removed. In debug, se next appartiene a un altro network, controlla anche che quel network esista ancora
+    unsafe fn hides(&self, next: *mut Node<T, G>) -> bool {
+        if self.is_dead(next) {
+            return true;
+        }
+
+        #[cfg(debug_assertions)]
+        if let Some(generation) = self.foreign.get(&next) {
+            assert!(
+                LIVE_GENERATIONS.lock().unwrap().contains(generation),
+                "arco verso il nodo {:#x} di un network già distrutto (generazione {})",
+                next as usize,
+                generation
+            );
+        }
+
+        (*next).removed
+    }
+
+    // true se next appartiene a un altro network. Da controllare solo dopo hides, che verifica che next esista ancora
+    unsafe fn crosses(&self, next: *mut Node<T, G>) -> bool {
+        (*next).generation != self.generation
+    }
+
+    // come hides, ma salta anche gli archi verso altri network: sono gli archi seguiti da visite e algoritmi
+    unsafe fn skips(&self, next: *mut Node<T, G>) -> bool {
+        self.hides(next) || self.crosses(next)
+    }
 }
 
 pub struct GenerationalGraph<'id, T, G> {
     nodes: Arena<Node<T, G>>,
+    // l'arena non permette di scorrere i nodi con un riferimento immutabile, quindi gli indirizzi dei nodi
+    // vengono salvati anche qui in ordine di allocazione
+    index: cell::RefCell<Vec<*mut Node<T, G>>>,
+    generation: u64,
+    // nodi creati con add_or_get raggruppati per hash del valore, usato per ritrovare un nodo con lo stesso valore
+    values: cell::RefCell<HashMap<u64, Vec<*mut Node<T, G>>>>,
     _marker: CovariantLifetime<'id>,
 }
 
@@ -33,169 +362,4524 @@ pub struct NodeRef<'a, 'id, 'b, T, G> {
     _marker3: ContravariantLifetime<'b>,
 }
 
-pub struct LinkHandle<'a, 'c, T, G> {
+// arco temporaneo creato da link_scoped, link_across o link_inner e rimosso quando il handle viene distrutto, oppure
+// riportato al costo previous se prima del handle esisteva già un arco verso la stessa destinazione. Il handle tiene
+// in prestito mutabile il NodeRef di partenza e il token del suo network, quindi mentre è vivo gli archi del nodo di
+// partenza sono modificabili solo attraverso il handle, e in prestito condiviso il NodeRef di destinazione, così non
+// può sopravvivere al network di destinazione
+pub struct LinkHandle<'h, 'id, T, G> {
     source: *mut Node<T, G>,
     dest: *mut Node<T, G>,
-    _marker1: InvariantLifetime<'a>,
-    _marker2: InvariantLifetime<'c>,
+    previous: Option<G>,
+    token: &'h mut GgToken<'id>,
+}
+
+// archi modificati durante una transaction. Per ogni modifica viene salvato lo stato precedente del primo arco e, se
+// erano stati rimossi, degli archi paralleli, così da poterli ripristinare in ordine inverso
+pub struct EdgeTxn<'id, T, G> {
+    members: HashMap<usize, *mut Node<T, G>>,
+    log: Vec<TxnEntry<T, G>>,
+    _marker: InvariantLifetime<'id>,
 }
 
+// modifica registrata da EdgeTxn: sorgente, destinazione, costo precedente del primo arco e archi paralleli rimossi
+type TxnEntry<T, G> = (*mut Node<T, G>, *mut Node<T, G>, Option<G>, Option<Vec<G>>);
+
+// archi aggiunti e archi rimossi restituiti da edge_diff
+type EdgeDiff = (Vec<(NodeId, NodeId)>, Vec<(NodeId, NodeId)>);
+
+// nodi di un network in ordine di allocazione e posizione di ciascun nodo in quell'ordine, restituiti da positions
+type Positions<T, G> = (Vec<*mut Node<T, G>>, HashMap<*mut Node<T, G>, usize>);
+
+#[repr(transparent)]
 pub struct NodeVisit<T, G> {
     ptr: *mut Node<T, G>,
 }
 
-impl<T, G> NodeVisit<T, G> {
-    fn links(&self) -> &HashMap<&NodeVisit<T, G>, G> {
-        unsafe {
-            mem::transmute(&(*self.ptr).links)
-        }
-    }
+// archi uscenti di un nodo come coppie (nodo di destinazione, costo), restituito da NodeVisit::neighbors e usato
+// da for (next, cost) in &node con un &NodeVisit
+pub struct Neighbors<'v, T, G> {
+    node: &'v Node<T, G>,
+    links: hash_map::Iter<'v, *mut Node<T, G>, G>,
 }
 
-impl<T, G> Hash for NodeVisit<T, G> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_usize(self.ptr as usize)
+// network in prestito insieme al suo token, restituito da view. Debug e Serialize leggono valori e costi di tutti i nodi
+// ma non possono ricevere il token come argomento, quindi sono implementati qui invece che sul network
+pub struct GraphView<'g, 'id, T, G> {
+    graph: &'g GenerationalGraph<'id, T, G>,
+    _token: &'g GgToken<'id>,
+}
+
+// identità di un nodo, ottenibile solo da NodeRef::id o NodeVisit::id. Contiene l'indirizzo del nodo ma non
+// permette di costruirne una a partire da un intero arbitrario
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+// risultato di add_or_get: il NodeRef del nodo appena creato, oppure l'identità del nodo con lo stesso valore che
+// esisteva già (per cui non può essere restituito un secondo NodeRef)
+pub enum AddOrGet<'a, 'id, 'b, T, G> {
+    Inserted(NodeRef<'a, 'id, 'b, T, G>),
+    Existing(NodeId),
+}
+
+// statistiche di base di un network restituite da stats. Per un network vuoto gradi e media valgono zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub max_out_degree: usize,
+    pub min_out_degree: usize,
+    pub avg_out_degree: f64,
+}
+
+// errore restituito dagli algoritmi che richiedono un grafo aciclico, node è un nodo che fa parte del ciclo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleError {
+    pub node: NodeId,
+}
+
+// errore restituito da bellman_ford quando dalla sorgente è raggiungibile un ciclo di costo negativo,
+// node è un nodo la cui distanza continua a diminuire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegativeCycleError {
+    pub node: NodeId,
+}
+
+// errore restituito da link_new quando esiste già un arco da from a to. Per aggiungere comunque un arco parallelo
+// accanto a quello esistente serve link_multi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeExistsError {
+    pub from: NodeId,
+    pub to: NodeId,
+}
+
+// errore restituito dai metodi che ricevono nodi come NodeId quando node non è (o non è più) un nodo del network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownNodeError {
+    pub node: NodeId,
+}
+
+// errore restituito da from_graphml quando il documento non è GraphML valido o non descrive un network diretto,
+// reason descrive il problema
+#[cfg(feature = "graphml")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphMlError {
+    pub reason: String,
+}
+
+// forma serializzata di un network: i nodi sono identificati dalla loro posizione in nodes (ordine di allocazione)
+// e ogni arco è una tripla (sorgente, destinazione, costo) di posizioni
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SerializedGraph<T, G> {
+    nodes: Vec<T>,
+    edges: Vec<(usize, usize, G)>,
+}
+
+// escape di una stringa da inserire tra virgolette in un file dot
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// escape di una stringa da inserire nel testo o tra virgolette in un documento XML
+#[cfg(feature = "graphml")]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+// inverso di xml_escape, riconosce anche i riferimenti numerici a caratteri (&#38; e &#x26;)
+#[cfg(feature = "graphml")]
+fn xml_unescape(text: &str) -> Result<String, GraphMlError> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest
+            .find(';')
+            .ok_or_else(|| GraphMlError { reason: format!("riferimento non terminato in \"{}\"", text) })?;
+        let character = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            reference if reference.starts_with("#x") => {
+                u32::from_str_radix(&reference[2..], 16).ok().and_then(char::from_u32)
+            }
+            reference if reference.starts_with('#') => reference[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+
+        let character = character
+            .ok_or_else(|| GraphMlError { reason: format!("riferimento sconosciuto {}", &rest[..=end]) })?;
+        result.push(character);
+        rest = &rest[end + 1..];
     }
+
+    result.push_str(rest);
+    Ok(result)
 }
 
-impl<'id, T, G> GenerationalGraph<'id, T, G> {
-    // implementazione della new di un network. La chiusura serve per brandizzare il network e tutti i nodi generati da esso
-    // e per impedire che essi possano essere utilizzati in metodi di altri network direttamente, il token di autorizzazione
-    // invece serve a controllare l'accesso mutabile o immutabile perchè come possiamo notare lo smart pointer richiede un riferimento
-    // immutabile per eseguire modifiche mutabili cosa che senza token violerebbe le regole di Rust Safe.
-    pub fn new(f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>) -> ()) {
-        f(GenerationalGraph {
-            nodes: Arena::new(),
-            _marker: CovariantLifetime(PhantomData),
-        },
-          GgToken {
-              _marker: InvariantLifetime(PhantomData),
-          })
+// nome e attributi (già senza escape) del tag contenuto tra < e >, senza gli eventuali / iniziale e finale
+#[cfg(feature = "graphml")]
+fn xml_tag(tag: &str) -> Result<(&str, HashMap<&str, String>), GraphMlError> {
+    let tag = tag.trim_start_matches('/').trim_end_matches('/').trim();
+    let split = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let (name, mut rest) = tag.split_at(split);
+    let mut attributes = HashMap::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        if rest.is_empty() {
+            return Ok((name, attributes));
+        }
+
+        let invalid = || GraphMlError { reason: format!("attributi non validi nel tag <{}>", tag) };
+        let equals = rest.find('=').ok_or_else(invalid)?;
+        let key = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'').ok_or_else(invalid)?;
+        let end = value[1..].find(quote).ok_or_else(invalid)? + 1;
+
+        attributes.insert(key, xml_unescape(&value[1..end])?);
+        rest = &value[end + 1..];
     }
+}
 
-    // crea un nuovo nodo e ritorna un riferimento mutabile al nodo (riferimento inteso come struttura che permette Deref mutabile)
-    pub fn add<'a>(&'a self, val: T, token: &mut GgToken<'id>) -> NodeRef<'a, 'id, 'a, T, G> {
-        let node = self.nodes.alloc(
-            Node {
-                value: val,
-                links: HashMap::new(),
-            });
+// union-find sugli indici di allocazione dei nodi, usato dagli algoritmi che raggruppano i nodi
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+}
 
-        NodeRef {
-            ptr: node as *mut Node<T, G>,
-            _marker1: CovariantLifetime(PhantomData),
-            _marker2: InvariantLifetime(PhantomData),
-            _marker3: ContravariantLifetime(PhantomData),
+impl UnionFind {
+    fn new(size: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
         }
     }
 
-    pub fn visit<R>(&self, root: &NodeRef<'_, '_, '_, T, G> , each: fn(&NodeVisit<T, G>) -> R) {
-        unsafe {
-            each(mem::transmute(root));
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
         }
+
+        x
     }
 
-    pub fn visit_multiple<R>(&self, roots: Vec<&NodeRef<'_, '_, '_, T, G>> , each: fn(Vec<&NodeVisit<T, G>>) -> R) {
-        unsafe {
-            each(mem::transmute(roots));
+    // unisce gli insiemi di a e b, ritorna false se erano già lo stesso insieme
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a == b {
+            return false;
+        }
+
+        if self.rank[a] < self.rank[b] {
+            self.parent[a] = b;
+        } else {
+            self.parent[b] = a;
+            if self.rank[a] == self.rank[b] {
+                self.rank[a] += 1;
+            }
         }
+
+        true
     }
 }
 
-impl<'a, 'id, 'b, T, G> Deref for NodeRef<'a, 'id, 'b, T, G> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
+impl<T, G> NodeVisit<T, G> {
+    // valore contenuto nel nodo, il riferimento è legato al NodeVisit quindi non può sopravvivere alla visita
+    pub fn value(&self) -> &T {
         unsafe { &(*self.ptr).value }
     }
-}
 
-impl<'a, 'id, 'b, T, G> DerefMut for NodeRef<'a, 'id, 'b, T, G> {
-    
-    fn deref_mut(&mut self) -> &mut Self::Target {
+    // valore del nodo in forma mutabile. Un NodeVisit mutabile si ottiene solo da visit_mut, che prende in prestito
+    // mutabile il NodeRef del nodo e il token, quindi mentre il riferimento è vivo il valore non è raggiungibile da
+    // altre parti. I NodeVisit ottenuti da neighbors sono in prestito da self e quindi non possono coesistere con
+    // value_mut
+    pub fn value_mut(&mut self) -> &mut T {
         unsafe { &mut (*self.ptr).value }
     }
-}
 
-impl<'a, 'id, 'b, T, G> NodeRef<'a, 'id, 'b, T, G> {
-    pub fn link(&mut self, other: &NodeRef<'a, 'id, '_, T, G>, cost: G) {
-        unsafe { (*self.ptr).links.insert(other.ptr, cost); }
+    // archi uscenti del nodo come coppie (nodo di destinazione, costo). I NodeVisit restituiti sono le chiavi stesse
+    // della mappa degli archi viste attraverso NodeVisit (che ha lo stesso layout del puntatore), quindi sono validi
+    // per tutta la durata del prestito di self. Viene convertita una chiave alla volta: la mappa intera non può essere
+    // vista come HashMap<&NodeVisit, G> perché il layout di HashMap non è garantito uguale al variare della chiave.
+    // Gli archi verso altri network vengono saltati, perché i loro nodi sono protetti dal token di un altro network
+    pub fn neighbors(&self) -> Neighbors<'_, T, G> {
+        let node = unsafe { &*self.ptr };
+        Neighbors { node, links: node.links.iter() }
     }
 
-    pub fn link_outer(&mut self, other: &NodeRef<'a, '_, '_, T, G>, cost: G) {
-        unsafe { (*self.ptr).links.insert(other.ptr, cost); }
+    // come neighbors ma con il valore del nodo di destinazione al posto del suo NodeVisit, nella forma (valore, costo)
+    pub fn neighbor_values(&self) -> impl Iterator<Item = (&T, &G)> {
+        self.neighbors().map(|(dest, cost)| (dest.value(), cost))
     }
 
-    pub fn link_inner<'c>(&mut self, other: &NodeRef<'c, '_, 'a, T, G>, cost: G) -> LinkHandle<'a, 'c, T, G> {
-        unsafe {
-            (*self.ptr).links.insert(other.ptr, cost);
+    pub fn id(&self) -> NodeId {
+        NodeId(self.ptr as usize)
+    }
 
-            LinkHandle {
-                source: self.ptr,
-                dest: other.ptr,
-                _marker1: InvariantLifetime(PhantomData),
-                _marker2: InvariantLifetime(PhantomData),
-            }
-        }
+    // come NodeRef::index
+    pub fn index(&self) -> usize {
+        unsafe { (*self.ptr).serial }
     }
 
-    pub fn unlink(&mut self, other: &NodeRef<'_, '_, '_, T, G>) {
-        unsafe { (*self.ptr).links.remove(&other.ptr); }
+    // somma dei costi degli archi uscenti dal nodo, G::default() se non ce ne sono
+    pub fn total_out_cost(&self) -> G
+    where
+        G: Clone + Add<Output = G> + Default,
+    {
+        unsafe { (*self.ptr).live_edges().fold(G::default(), |total, (_, cost)| total + cost.clone()) }
     }
 
-    pub fn weight_of<'w>(&'w self, dest: usize) -> Option<&'w G> {
-        unsafe {
-            (*self.ptr).links.get(&(dest as *mut Node<T, G>))
-        }
+    // true se esiste un arco da questo nodo a other
+    pub fn is_linked_to(&self, other: &NodeVisit<T, G>) -> bool {
+        unsafe { (*self.ptr).link_to(other.ptr).is_some() }
     }
 
-    pub fn weight_of_mut<'w>(&'w mut self, dest: usize) -> Option<&'w mut G> {
-        unsafe {
-            (*self.ptr).links.get_mut(&(dest as *mut Node<T, G>))
-        }
+    fn wrap(ptr: &*mut Node<T, G>) -> &NodeVisit<T, G> {
+        unsafe { &*(ptr as *const *mut Node<T, G> as *const NodeVisit<T, G>) }
     }
+}
 
-    pub fn link_self(&mut self, cost: G) {
-        unsafe {
-            (*self.ptr).links.insert(self.ptr, cost);
-        }
+impl<'v, T, G> Iterator for Neighbors<'v, T, G> {
+    type Item = (&'v NodeVisit<T, G>, &'v G);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node;
+        self.links.find(|(&dest, _)| unsafe { !node.skips(dest) }).map(|(dest, cost)| (NodeVisit::wrap(dest), cost))
     }
+}
 
-    pub fn unlink_self(&mut self) {
-        unsafe {
-            (*self.ptr).links.remove(&self.ptr);
-        }
+impl<'v, T, G> IntoIterator for &'v NodeVisit<T, G> {
+    type Item = (&'v NodeVisit<T, G>, &'v G);
+    type IntoIter = Neighbors<'v, T, G>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.neighbors()
     }
 }
 
-impl<'a, 'c, T, G> Drop for LinkHandle<'a, 'c, T, G> {
-    fn drop(&mut self) {
-        unsafe {
-            (*self.source).links.remove(&self.dest);
-        }
+// come edges(): le destinazioni sono restituite come NodeId, perché leggere i loro valori e i loro archi richiede il
+// token del network
+impl<'v, T, G> IntoIterator for &'v NodeRef<'_, '_, '_, T, G> {
+    type Item = (NodeId, &'v G);
+    type IntoIter = Box<dyn Iterator<Item = (NodeId, &'v G)> + 'v>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.edges())
     }
 }
 
-unsafe impl Send for GgToken<'_> {}
-unsafe impl Sync for GgToken<'_> {}
+impl<T, G> Hash for NodeVisit<T, G> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.ptr as usize)
+    }
+}
+
+// due NodeVisit sono uguali se si riferiscono allo stesso nodo, coerentemente con hash
+impl<T, G> PartialEq for NodeVisit<T, G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
 
-unsafe impl<T: Sync, G: Sync> Sync for GenerationalGraph<'_, T, G> {}
-unsafe impl<T: Sync, G: Sync> Sync for NodeRef<'_, '_, '_, T, G> {}
+impl<T, G> Eq for NodeVisit<T, G> {}
 
-fn main() {
-    GenerationalGraph::new(|graph1, mut token1| {
-        let mut x1 = graph1.add(1, &mut token1);
-        let mut x2 = graph1.add(1, &mut token1);
-        x1.link(&x2, 2);
+impl<'id, T, G> GenerationalGraph<'id, T, G> {
+    // implementazione della new di un network. La chiusura serve per brandizzare il network e tutti i nodi generati da esso
+    // e per impedire che essi possano essere utilizzati in metodi di altri network direttamente, il token di autorizzazione
+    // invece serve a controllare l'accesso mutabile o immutabile perchè come possiamo notare lo smart pointer richiede un riferimento
+    // immutabile per eseguire modifiche mutabili cosa che senza token violerebbe le regole di Rust Safe.
+    pub fn new(f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>) -> ()) {
+        f(GenerationalGraph {
+            nodes: Arena::new(),
+            index: cell::RefCell::new(Vec::new()),
+            values: cell::RefCell::new(HashMap::new()),
+            generation: register_generation(),
+            _marker: CovariantLifetime(PhantomData),
+        },
+          GgToken {
+              _marker: InvariantLifetime(PhantomData),
+          })
+    }
+
+    // come new ma l'arena (e l'indice dei nodi) riservano subito lo spazio per capacity nodi, così che un caricamento
+    // di molti nodi non debba far crescere l'arena più volte
+    pub fn with_capacity(capacity: usize, f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>)) {
+        f(GenerationalGraph {
+            nodes: Arena::with_capacity(capacity),
+            index: cell::RefCell::new(Vec::with_capacity(capacity)),
+            values: cell::RefCell::new(HashMap::new()),
+            generation: register_generation(),
+            _marker: CovariantLifetime(PhantomData),
+        },
+          GgToken {
+              _marker: InvariantLifetime(PhantomData),
+          })
+    }
 
-        graph1.visit(&x1, |root| {});
+    // ricostruisce un network serializzato: i nodi vengono allocati nell'ordine in cui sono stati serializzati e
+    // collegati come con link_multi (quindi gli archi paralleli vengono ripristinati), poi il network viene passato
+    // alla chiusura come in new. Se un arco fa riferimento a un nodo inesistente viene restituito un errore e la
+    // chiusura non viene eseguita
+    #[cfg(feature = "serde")]
+    pub fn from_deserializer<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>),
+    ) -> result::Result<(), D::Error>
+    where
+        T: DeserializeOwned,
+        G: DeserializeOwned,
+    {
+        let data = SerializedGraph::<T, G>::deserialize(deserializer)?;
 
-        GenerationalGraph::new(|graph2, mut token2| {
-            let mut y1 = graph2.add(1, &mut token2);
-            y1.link_outer(&x1, 1);
-            let handle = x1.link_inner(&y1, 1);
+        let count = data.nodes.len();
 
-            // ...
+        if let Some(&(source, dest, _)) = data.edges.iter().find(|(source, dest, _)| *source >= count || *dest >= count) {
+            return Err(de::Error::custom(format!("l'arco {} -> {} fa riferimento a un nodo inesistente", source, dest)));
+        }
+
+        GenerationalGraph::new(move |graph, mut token| {
+            {
+                let nodes: Vec<_> = data.nodes.into_iter().map(|value| graph.add(value, &mut token)).collect();
+
+                for (source, dest, cost) in data.edges {
+                    let (source, dest) = (nodes[source].ptr, nodes[dest].ptr);
+                    unsafe { (*source).push_link(dest, cost); }
+                }
+            }
+
+            f(graph, token)
         });
-    });
+
+        Ok(())
+    }
+
+    // ricostruisce un network da un documento GraphML, ad esempio prodotto da to_graphml: i nodi vengono allocati
+    // nell'ordine in cui compaiono e gli archi aggiunti come con link_multi, poi il network viene passato alla chiusura
+    // come in new. Il valore di ogni nodo è il testo del suo attributo "value" convertito da value, il costo di ogni
+    // arco quello del suo attributo "cost" convertito da cost (gli attributi sono riconosciuti dal loro attr.name, o
+    // dall'id della chiave se non è dichiarata). Viene letto solo il primo livello di nodi e archi: i grafi annidati,
+    // le porte e gli iperarchi non sono supportati. Se il documento non è valido, è un grafo non diretto, un arco fa
+    // riferimento a un nodo inesistente o un valore manca o non può essere convertito viene restituito un errore e la
+    // chiusura non viene eseguita
+    #[cfg(feature = "graphml")]
+    pub fn from_graphml(
+        xml: &str,
+        value: impl Fn(&str) -> Option<T>,
+        cost: impl Fn(&str) -> Option<G>,
+        f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>),
+    ) -> result::Result<(), GraphMlError> {
+        let error = |reason: String| GraphMlError { reason };
+        let mut keys = HashMap::new();
+        // id e testo dell'attributo "value" di ogni nodo, estremi e testo dell'attributo "cost" di ogni arco
+        let mut node_data: Vec<(String, Option<String>)> = Vec::new();
+        let mut edge_data: Vec<(String, String, Option<String>)> = Vec::new();
+        // elemento aperto (0 nessuno, 1 nodo, 2 arco) e attributo di cui si sta leggendo il testo
+        let mut open = 0;
+        let mut data: Option<(String, String)> = None;
+        let mut depth = 0;
+        let mut rest = xml;
+
+        while let Some(start) = rest.find('<') {
+            if let Some((_, text)) = data.as_mut() {
+                text.push_str(&xml_unescape(&rest[..start])?);
+            }
+
+            rest = &rest[start..];
+
+            let skip = |rest: &str, end: &str| {
+                rest.find(end).map(|i| i + end.len()).ok_or_else(|| error(format!("{} mancante", end)))
+            };
+
+            if rest.starts_with("<!--") {
+                rest = &rest[skip(rest, "-->")?..];
+                continue;
+            }
+
+            if rest.starts_with("<![CDATA[") {
+                let end = skip(rest, "]]>")?;
+                if let Some((_, text)) = data.as_mut() {
+                    text.push_str(&rest[9..end - 3]);
+                }
+                rest = &rest[end..];
+                continue;
+            }
+
+            if rest.starts_with("<?") || rest.starts_with("<!") {
+                rest = &rest[skip(rest, if rest.starts_with("<?") { "?>" } else { ">" })?..];
+                continue;
+            }
+
+            // il > che chiude il tag è il primo fuori dalle virgolette
+            let mut quote = None;
+            let end = rest.char_indices()
+                .find(|&(_, c)| match quote {
+                    Some(q) if c == q => { quote = None; false }
+                    Some(_) => false,
+                    None if c == '"' || c == '\'' => { quote = Some(c); false }
+                    None => c == '>',
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(|| error(String::from("tag non terminato")))?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let empty = tag.ends_with('/');
+            let (name, mut attributes) = xml_tag(tag)?;
+            let name = name.rsplit(':').next().unwrap_or(name);
+
+            if closing {
+                match name {
+                    "graph" => depth -= 1,
+                    "node" | "edge" if depth == 1 => open = 0,
+                    "data" => {
+                        if let Some((key, text)) = data.take() {
+                            let key = keys.get(&key).cloned().unwrap_or(key);
+
+                            match (open, key.as_str()) {
+                                (1, "value") => node_data.last_mut().unwrap().1 = Some(text),
+                                (2, "cost") => edge_data.last_mut().unwrap().2 = Some(text),
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            match name {
+                "key" => {
+                    if let Some(id) = attributes.remove("id") {
+                        let name = attributes.remove("attr.name").unwrap_or_else(|| id.clone());
+                        keys.insert(id, name);
+                    }
+                }
+                "graph" => {
+                    if depth == 0 && attributes.get("edgedefault").map(String::as_str) == Some("undirected") {
+                        return Err(error(String::from("i grafi non diretti non sono supportati")));
+                    }
+                    if !empty {
+                        depth += 1;
+                    }
+                }
+                "node" if depth == 1 => {
+                    let id = attributes.remove("id").ok_or_else(|| error(String::from("nodo senza id")))?;
+                    node_data.push((id, None));
+                    open = if empty { 0 } else { 1 };
+                }
+                "edge" if depth == 1 => {
+                    if attributes.get("directed").map(String::as_str) == Some("false") {
+                        return Err(error(String::from("gli archi non diretti non sono supportati")));
+                    }
+                    let mut endpoint = |name| {
+                        attributes.remove(name).ok_or_else(|| error(format!("arco senza {}", name)))
+                    };
+                    let (source, target) = (endpoint("source")?, endpoint("target")?);
+                    edge_data.push((source, target, None));
+                    open = if empty { 0 } else { 2 };
+                }
+                "data" if depth == 1 && open != 0 && !empty => {
+                    data = attributes.remove("key").map(|key| (key, String::new()));
+                }
+                _ => {}
+            }
+        }
+
+        let mut positions = HashMap::new();
+        let mut values = Vec::with_capacity(node_data.len());
+
+        for (i, (id, text)) in node_data.into_iter().enumerate() {
+            let text = text.ok_or_else(|| error(format!("il nodo {} non ha un valore", id)))?;
+            values.push(value(&text).ok_or_else(|| error(format!("valore non valido per il nodo {}: {}", id, text)))?);
+
+            if positions.insert(id.clone(), i).is_some() {
+                return Err(error(format!("il nodo {} compare più volte", id)));
+            }
+        }
+
+        let mut edges = Vec::with_capacity(edge_data.len());
+
+        for (source, target, text) in edge_data {
+            let position = |id: &String| {
+                positions.get(id).copied().ok_or_else(|| {
+                    error(format!("l'arco {} -> {} fa riferimento a un nodo inesistente", source, target))
+                })
+            };
+            let text = text.ok_or_else(|| error(format!("l'arco {} -> {} non ha un costo", source, target)))?;
+            let edge_cost = cost(&text)
+                .ok_or_else(|| error(format!("costo non valido per l'arco {} -> {}: {}", source, target, text)))?;
+            edges.push((position(&source)?, position(&target)?, edge_cost));
+        }
+
+        GenerationalGraph::with_capacity(values.len(), move |graph, mut token| {
+            {
+                let nodes: Vec<_> = values.into_iter().map(|value| graph.add(value, &mut token).ptr).collect();
+
+                for (source, dest, cost) in edges {
+                    let (source, dest) = (nodes[source], nodes[dest]);
+                    unsafe { (*source).push_link(dest, cost); }
+                }
+            }
+
+            f(graph, token)
+        });
+
+        Ok(())
+    }
+
+    // costruisce un network a partire da una lista di archi con estremi identificati da interi esterni: viene creato un
+    // nodo (con valore value_for(id)) per ogni id distinto nell'ordine in cui compare per la prima volta, poi gli archi
+    // vengono aggiunti come con link_multi. La mappa dagli id esterni ai NodeId viene passata alla chiusura insieme al
+    // network perché le identità dei nodi hanno senso solo finché il network esiste
+    pub fn from_edges(
+        edges: &[(u64, u64, G)],
+        value_for: impl Fn(u64) -> T,
+        f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>, HashMap<u64, NodeId>),
+    ) where
+        G: Clone,
+    {
+        GenerationalGraph::new(move |graph, mut token| {
+            let mut ids = HashMap::new();
+
+            {
+                let mut nodes = HashMap::new();
+
+                for &(source, dest, _) in edges.iter() {
+                    for id in [source, dest] {
+                        if let Entry::Vacant(entry) = nodes.entry(id) {
+                            let node = graph.add(value_for(id), &mut token);
+                            ids.insert(id, node.id());
+                            entry.insert(node.ptr);
+                        }
+                    }
+                }
+
+                for (source, dest, cost) in edges.iter() {
+                    unsafe { (*nodes[source]).push_link(nodes[dest], cost.clone()); }
+                }
+            }
+
+            f(graph, token, ids)
+        });
+    }
+
+    // costruisce un network casuale di Erdős–Rényi con n nodi (con valore value_for(i)) in cui ogni possibile arco
+    // diretto i -> j con i != j è presente con probabilità edge_prob e costo cost_for(i, j), poi il network viene
+    // passato alla chiusura come in new. I cappi non vengono generati. Il generatore è fornito dal chiamante così che
+    // con un seme fissato il network sia riproducibile. Va in panic se edge_prob non è compreso tra 0 e 1
+    #[cfg(feature = "rand")]
+    pub fn random<R: Rng + ?Sized>(
+        n: usize,
+        edge_prob: f64,
+        rng: &mut R,
+        value_for: impl Fn(usize) -> T,
+        mut cost_for: impl FnMut(usize, usize) -> G,
+        f: impl for<'a> FnOnce(GenerationalGraph<'a, T, G>, GgToken<'a>),
+    ) {
+        GenerationalGraph::with_capacity(n, move |graph, mut token| {
+            {
+                let nodes: Vec<_> = (0..n).map(|i| graph.add(value_for(i), &mut token).ptr).collect();
+
+                for i in 0..n {
+                    for j in 0..n {
+                        if i != j && rng.gen_bool(edge_prob) {
+                            let (source, dest) = (nodes[i], nodes[j]);
+                            unsafe { (*source).insert_link(dest, cost_for(i, j)); }
+                        }
+                    }
+                }
+            }
+
+            f(graph, token)
+        });
+    }
+
+    // crea un nuovo nodo e ritorna il suo NodeRef, con cui se ne legge il valore (Deref) e, insieme al token, lo si
+    // modifica (value_mut) e se ne creano gli archi
+    pub fn add<'a>(&'a self, val: T, token: &mut GgToken<'id>) -> NodeRef<'a, 'id, 'a, T, G> {
+        let serial = self.nodes.len();
+        let node = self.nodes.alloc(
+            Node {
+                value: val,
+                links: HashMap::default(),
+                this: null_mut(),
+                foreign_sources: cell::UnsafeCell::new(Vec::new()),
+                dead_links: cell::UnsafeCell::new(Vec::new()),
+                parallel: HashMap::default(),
+                removed: false,
+                serial,
+                generation: self.generation,
+                #[cfg(debug_assertions)]
+                foreign: HashMap::default(),
+            });
+        let ptr = node as *mut Node<T, G>;
+        unsafe { (*ptr).this = ptr; }
+        self.index.borrow_mut().push(ptr);
+
+        NodeRef {
+            ptr,
+            _marker1: CovariantLifetime(PhantomData),
+            _marker2: InvariantLifetime(PhantomData),
+            _marker3: ContravariantLifetime(PhantomData),
+        }
+    }
+
+    // come add, ma se esiste già un nodo creato con add_or_get con un valore uguale a val non viene allocato un
+    // duplicato e viene restituita (come AddOrGet::Existing) l'identità del nodo esistente. Restituire un secondo
+    // NodeRef dello stesso nodo permetterebbe due riferimenti mutabili al suo valore, quindi il NodeRef esiste solo per
+    // il nodo appena creato. Per riconoscere i duplicati il network tiene un indice aggiuntivo dei nodi per hash del
+    // valore, che cresce con ogni nodo creato da add_or_get; i nodi creati con add non ne fanno parte. Il confronto usa
+    // il valore attuale del nodo, quindi un valore modificato dopo la creazione non viene più riconosciuto come uguale
+    // a quello iniziale. Vengono letti i valori degli altri nodi: il token in prestito mutabile garantisce che nessun
+    // value_mut sia vivo
+    pub fn add_or_get<'a>(&'a self, val: T, token: &mut GgToken<'id>) -> AddOrGet<'a, 'id, 'a, T, G>
+    where
+        T: Hash + Eq,
+    {
+        let mut hasher = DefaultHasher::new();
+        val.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if let Some(nodes) = self.values.borrow().get(&hash) {
+            if let Some(&ptr) = nodes.iter().find(|&&ptr| unsafe { !(*ptr).removed && (*ptr).value == val }) {
+                return AddOrGet::Existing(NodeId(ptr as usize));
+            }
+        }
+
+        let node = self.add(val, token);
+        self.values.borrow_mut().entry(hash).or_default().push(node.ptr);
+        AddOrGet::Inserted(node)
+    }
+
+    // come add_edges per network senza pesi: ogni arco (sorgente, destinazione) riceve il costo G::default()
+    pub fn add_unweighted(
+        &mut self,
+        edges: &[(NodeId, NodeId)],
+        _token: &mut GgToken<'id>,
+    ) -> Result<(), UnknownNodeError>
+    where
+        G: Default,
+    {
+        let members = self.members();
+        let edges = edges.iter()
+            .map(|&(source, dest)| Ok((Self::resolve(&members, source)?, Self::resolve(&members, dest)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (source, dest) in edges {
+            unsafe { (*source).insert_link(dest, G::default()); }
+        }
+
+        Ok(())
+    }
+
+    // inserisce tutti gli archi (sorgente, destinazione, costo) di edges, come tante chiamate a link. Gli estremi sono
+    // NodeId perché i NodeRef tengono in prestito il network, che qui è preso in prestito mutabile: così nessun
+    // riferimento ai nodi può essere vivo mentre i loro archi vengono modificati. Se un estremo non è un nodo di questo
+    // network (ad esempio perché rimosso con mark_removed) viene restituito un errore e nessun arco viene inserito
+    pub fn add_edges(
+        &mut self,
+        edges: &[(NodeId, NodeId, G)],
+        _token: &mut GgToken<'id>,
+    ) -> Result<(), UnknownNodeError>
+    where
+        G: Clone,
+    {
+        let members = self.members();
+        let edges = edges.iter()
+            .map(|(source, dest, cost)| Ok((Self::resolve(&members, *source)?, Self::resolve(&members, *dest)?, cost)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (source, dest, cost) in edges {
+            unsafe { (*source).insert_link(dest, cost.clone()); }
+        }
+
+        Ok(())
+    }
+
+    // nodi del network per indirizzo, per controllare i NodeId ricevuti dai metodi che modificano più nodi insieme
+    fn members(&self) -> HashMap<usize, *mut Node<T, G>> {
+        self.index.borrow().iter().map(|&ptr| (ptr as usize, ptr)).collect()
+    }
+
+    // nodo identificato da id, UnknownNodeError se non è tra members
+    fn resolve(members: &HashMap<usize, *mut Node<T, G>>, id: NodeId) -> Result<*mut Node<T, G>, UnknownNodeError> {
+        members.get(&id.0).copied().ok_or(UnknownNodeError { node: id })
+    }
+
+    // esegue f, che può aggiungere e rimuovere archi attraverso l'EdgeTxn ricevuto: se f restituisce un errore tutte le
+    // modifiche fatte vengono annullate in ordine inverso e l'errore viene restituito, altrimenti restano tutte. Come
+    // add_edges prende &mut self e l'EdgeTxn indica i nodi con NodeId, così nessun NodeRef può essere vivo durante f
+    pub fn transaction<E>(
+        &mut self,
+        _token: &mut GgToken<'id>,
+        f: impl FnOnce(&mut EdgeTxn<'id, T, G>) -> result::Result<(), E>,
+    ) -> result::Result<(), E> {
+        let mut txn = EdgeTxn { members: self.members(), log: Vec::new(), _marker: InvariantLifetime(PhantomData) };
+        let outcome = f(&mut txn);
+
+        if outcome.is_err() {
+            for (source, dest, primary, parallel) in txn.log.into_iter().rev() {
+                unsafe {
+                    match primary {
+                        Some(cost) => (*source).insert_link(dest, cost),
+                        None => (*source).links.remove(&dest),
+                    };
+
+                    if let Some(costs) = parallel {
+                        (*source).parallel.insert(dest, costs);
+                    }
+                }
+            }
+        }
+
+        outcome
+    }
+
+    // il network insieme al token con cui leggerne valori e costi, per stamparlo con {:?} o serializzarlo
+    pub fn view<'g>(&'g self, token: &'g GgToken<'id>) -> GraphView<'g, 'id, T, G> {
+        GraphView { graph: self, _token: token }
+    }
+
+    // numero di nodi del network, cioè i nodi creati con add dalla creazione del network esclusi quelli rimossi con
+    // mark_removed (che comunque restano allocati nell'arena)
+    pub fn node_count(&self) -> usize {
+        self.index.borrow().len()
+    }
+
+    // rimuove node dal network senza liberarne la memoria, che l'arena rilascia solo quando il network viene distrutto.
+    // Vengono rimossi gli archi uscenti dal nodo e quelli entranti dai nodi di questo network (scorrendo tutti i nodi,
+    // O(V)); il nodo esce dall'indice quindi iteratori e algoritmi sull'intero network non lo vedono più. Prende
+    // &mut self, quindi nessun NodeRef del network è vivo: il nodo è indicato dal suo NodeId e, una volta rimosso, non
+    // esiste un NodeRef con cui creare nuovi archi da o verso di lui. Gli archi verso il nodo da altri network (link_inner,
+    // link_across, link_outer) diventano archi morti come se il nodo fosse stato distrutto, perché le mappe dei nodi di
+    // partenza potrebbero essere in prestito: restano lì ma le visite e gli iteratori sugli archi li saltano. Se node non
+    // è un nodo del network (o è già stato rimosso) viene restituito un errore
+    pub fn mark_removed(&mut self, node: NodeId, _token: &mut GgToken<'id>) -> Result<(), UnknownNodeError> {
+        let ptr = Self::resolve(&self.members(), node)?;
+        let index = self.index.get_mut();
+        index.retain(|&other| other != ptr);
+
+        unsafe {
+            for &other in index.iter() {
+                (*other).remove_links(ptr);
+            }
+
+            (*ptr).clear_links();
+            (*ptr).release();
+            (*ptr).removed = true;
+        }
+
+        Ok(())
+    }
+
+    // stima dei byte occupati dal network: i nodi dell'arena (compresi quelli rimossi con mark_removed, che restano
+    // allocati) per size_of::<Node>, più per ogni arco contato da edge_count una voce di mappa (indirizzo e costo più
+    // il byte di controllo della tabella hash), più l'indice dei nodi. È un'approssimazione per difetto: non conta
+    // lo spazio libero nei blocchi dell'arena e nelle tabelle hash (che non sono mai piene), la memoria posseduta da
+    // T e G fuori dal nodo, né la mappa dei valori di add_or_get
+    pub fn memory_usage(&self) -> usize {
+        let nodes = self.nodes.len() * mem::size_of::<Node<T, G>>();
+        let edges = self.edge_count() * (mem::size_of::<(*mut Node<T, G>, G)>() + 1);
+        let index = self.index.borrow().capacity() * mem::size_of::<*mut Node<T, G>>();
+
+        nodes + edges + index
+    }
+
+    // numero di archi del network, ovvero la somma degli archi uscenti di ogni nodo. Un cappio creato con
+    // link_self conta come un solo arco, gli archi paralleli creati con link_multi vengono contati tutti
+    pub fn edge_count(&self) -> usize {
+        self.index.borrow().iter()
+            .map(|&ptr| unsafe { (*ptr).live_count() + (*ptr).live_parallel().count() })
+            .sum()
+    }
+
+    // nodi del network in ordine di allocazione insieme alla posizione di ciascun nodo in quell'ordine
+    fn positions(&self) -> Positions<T, G> {
+        let nodes = self.index.borrow().clone();
+        let positions = nodes.iter().enumerate().map(|(i, &ptr)| (ptr, i)).collect();
+        (nodes, positions)
+    }
+
+    // numero di archi entranti in node provenienti da nodi di questo network. Gli archi sono salvati solo nel nodo
+    // di partenza, quindi bisogna controllare la mappa degli archi di ogni nodo: il costo è O(V + E)
+    pub fn in_degree(&self, node: &NodeRef<'_, 'id, '_, T, G>) -> usize {
+        self.index.borrow().iter().filter(|&&ptr| unsafe { (*ptr).link_to(node.ptr).is_some() }).count()
+    }
+
+    // nodi che hanno un arco verso sé stessi (creato ad esempio con link_self), in ordine di allocazione
+    pub fn self_loops(&self) -> Vec<NodeId> {
+        self.index.borrow().iter()
+            .filter(|&&ptr| unsafe { (*ptr).links.contains_key(&ptr) })
+            .map(|&ptr| NodeId(ptr as usize))
+            .collect()
+    }
+
+    // numero di nodi, numero di archi e grado uscente minimo, massimo e medio calcolati con una sola scansione dei nodi.
+    // Come in edge_count gli archi paralleli creati con link_multi contano tutti, sia nel numero di archi che nei gradi,
+    // così la media resta il numero di archi diviso per il numero di nodi
+    pub fn stats(&self) -> GraphStats {
+        let index = self.index.borrow();
+        let mut stats = GraphStats {
+            node_count: index.len(),
+            edge_count: 0,
+            max_out_degree: 0,
+            min_out_degree: if index.is_empty() { 0 } else { usize::MAX },
+            avg_out_degree: 0.0,
+        };
+
+        for &ptr in index.iter() {
+            let degree = unsafe { (*ptr).live_count() + (*ptr).live_parallel().count() };
+            stats.edge_count += degree;
+            stats.max_out_degree = stats.max_out_degree.max(degree);
+            stats.min_out_degree = stats.min_out_degree.min(degree);
+        }
+
+        if stats.node_count > 0 {
+            stats.avg_out_degree = stats.edge_count as f64 / stats.node_count as f64;
+        }
+
+        stats
+    }
+
+    // per ogni grado uscente il numero di nodi che lo hanno, ad esempio per verificare se la distribuzione dei gradi
+    // segue una legge di potenza. Il grado è il numero di destinazioni distinte come in out_degree
+    pub fn out_degree_histogram(&self) -> HashMap<usize, usize> {
+        let mut histogram = HashMap::new();
+
+        for &ptr in self.index.borrow().iter() {
+            *histogram.entry(unsafe { (*ptr).live_count() }).or_insert(0) += 1;
+        }
+
+        histogram
+    }
+
+    // come out_degree_histogram ma per il grado entrante, contato come in in_degree (solo archi da nodi di questo
+    // network). I gradi vengono accumulati con una sola scansione degli archi invece di chiamare in_degree per ogni
+    // nodo, quindi il costo è O(V + E) e non O(V * E)
+    pub fn in_degree_histogram(&self) -> HashMap<usize, usize> {
+        let (nodes, positions) = self.positions();
+        let mut degrees = vec![0; nodes.len()];
+
+        for &ptr in nodes.iter() {
+            unsafe {
+                for next in (*ptr).live_keys() {
+                    if let Some(&j) = positions.get(next) {
+                        degrees[j] += 1;
+                    }
+                }
+            }
+        }
+
+        let mut histogram = HashMap::new();
+
+        for degree in degrees {
+            *histogram.entry(degree).or_insert(0) += 1;
+        }
+
+        histogram
+    }
+
+    // nodi senza archi uscenti (pozzi), in ordine di allocazione. Come in out_degree conta ogni arco uscente, anche
+    // verso nodi di altri network
+    pub fn sinks(&self) -> Vec<NodeId> {
+        self.index.borrow().iter()
+            .filter(|&&ptr| unsafe { (*ptr).live_count() == 0 })
+            .map(|&ptr| NodeId(ptr as usize))
+            .collect()
+    }
+
+    // nodi senza archi entranti (sorgenti), in ordine di allocazione. Come in in_degree contano solo gli archi da nodi
+    // di questo network; le destinazioni vengono raccolte con una sola scansione degli archi, quindi il costo è O(V + E)
+    pub fn sources(&self) -> Vec<NodeId> {
+        self.without_in_edges(|_| true)
+    }
+
+    // nodi senza archi uscenti né entranti, in ordine di allocazione: spesso indicano errori nell'importazione dei dati.
+    // Un cappio conta sia come arco uscente che entrante, quindi un nodo con solo un cappio non è isolato
+    pub fn isolated_nodes(&self) -> Vec<NodeId> {
+        self.without_in_edges(|ptr| unsafe { (*ptr).live_count() == 0 })
+    }
+
+    // nodi che soddisfano keep e non hanno archi entranti da nodi di questo network
+    fn without_in_edges(&self, keep: impl Fn(*mut Node<T, G>) -> bool) -> Vec<NodeId> {
+        let index = self.index.borrow();
+        let targets: HashSet<_> = index.iter().flat_map(|&ptr| unsafe { (*ptr).live_keys().copied() }).collect();
+
+        index.iter()
+            .filter(|&&ptr| !targets.contains(&ptr) && keep(ptr))
+            .map(|&ptr| NodeId(ptr as usize))
+            .collect()
+    }
+
+    // rimuove tutti i cappi del network
+    pub fn remove_self_loops(&mut self, _token: &mut GgToken<'id>) {
+        for &ptr in self.index.borrow().iter() {
+            unsafe { (*ptr).remove_links(ptr); }
+        }
+    }
+
+    // liste di adiacenza orientate indicizzate per posizione di allocazione: j compare tra i successori di i se esiste
+    // un arco i -> j. Gli archi verso altri network vengono ignorati
+    fn adjacency(&self) -> (Vec<*mut Node<T, G>>, Vec<Vec<usize>>) {
+        let (nodes, positions) = self.positions();
+        let adjacency = nodes
+            .iter()
+            .map(|&ptr| unsafe { (*ptr).live_keys().filter_map(|next| positions.get(next).copied()).collect() })
+            .collect();
+
+        (nodes, adjacency)
+    }
+
+    // liste di adiacenza non orientate indicizzate per posizione di allocazione: j compare (una sola volta) tra i vicini
+    // di i se esiste un arco i -> j o j -> i. Gli archi verso altri network vengono ignorati
+    fn undirected_adjacency(&self) -> (Vec<*mut Node<T, G>>, Vec<Vec<usize>>) {
+        let (nodes, positions) = self.positions();
+        let mut adjacency = vec![Vec::new(); nodes.len()];
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for next in (*ptr).live_keys() {
+                    if let Some(&j) = positions.get(next) {
+                        adjacency[i].push(j);
+                        if i != j {
+                            adjacency[j].push(i);
+                        }
+                    }
+                }
+            }
+        }
+
+        for neighbors in adjacency.iter_mut() {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+
+        (nodes, adjacency)
+    }
+
+    // rimuove tutti gli archi uscenti da ogni nodo mantenendo i nodi, così da poter ricostruire la topologia senza
+    // allocare nuovi nodi nell'arena (che comunque non libera mai i singoli nodi)
+    pub fn clear_edges(&mut self, _token: &mut GgToken<'id>) {
+        for &ptr in self.index.get_mut().iter() {
+            unsafe { (*ptr).clear_links(); }
+        }
+    }
+
+    // distrugge tutti i nodi (compresi quelli rimossi con mark_removed) lasciando il network vuoto, così che possa
+    // essere riempito di nuovo senza ricreare il network, ad esempio in un ciclo che costruisce e scarta un grafo per
+    // ogni richiesta. L'arena non permette di riusare la memoria di nodi già allocati, quindi viene sostituita da una
+    // nuova arena con spazio per tanti nodi quanti ne aveva quella vecchia: riempire di nuovo il network richiede una sola
+    // allocazione invece di far crescere l'arena più volte, mentre indice e mappa dei valori mantengono la loro capacità.
+    // Come quando il network viene distrutto i nodi vengono separati dagli altri network: gli archi da altri network
+    // verso i vecchi nodi diventano archi morti, ignorati da visite e algoritmi. I NodeId ottenuti prima della
+    // chiamata non vanno più usati perché un nuovo nodo può riceverne uno uguale, e le posizioni restituite da
+    // NodeRef::index ripartono da zero. Il network riceve una nuova generazione
+    pub fn reset(&mut self, _token: &mut GgToken<'id>) {
+        let index = self.index.get_mut();
+
+        for &ptr in index.iter() {
+            unsafe { (*ptr).release(); }
+        }
+
+        index.clear();
+        self.values.get_mut().clear();
+        self.nodes = Arena::with_capacity(self.nodes.len());
+
+        #[cfg(debug_assertions)]
+        LIVE_GENERATIONS.lock().unwrap().retain(|&generation| generation != self.generation);
+        self.generation = register_generation();
+    }
+
+    // sostituisce il costo di ogni arco con f applicata al costo attuale, ad esempio per applicare un fattore di
+    // decadimento a tutti i pesi. Vengono aggiornati anche gli archi paralleli e quelli verso altri network
+    pub fn map_weights(&mut self, f: impl Fn(&G) -> G, _token: &mut GgToken<'id>) {
+        for &ptr in self.index.get_mut().iter() {
+            unsafe {
+                let node = &mut *ptr;
+
+                for cost in node.links.values_mut().chain(node.parallel.values_mut().flatten()) {
+                    *cost = f(cost);
+                }
+            }
+        }
+    }
+
+    // rimuove ogni arco il cui costo non soddisfa keep, ad esempio per eliminare i collegamenti deboli di un grafo di
+    // similarità, e ritorna il numero di archi rimossi. Vengono controllati anche gli archi paralleli e quelli verso
+    // altri network; se il primo arco verso una destinazione viene rimosso il primo parallelo rimasto prende il suo posto
+    pub fn retain_edges(&mut self, keep: impl Fn(&G) -> bool, _token: &mut GgToken<'id>) -> usize {
+        let mut removed = 0;
+
+        for &ptr in self.index.get_mut().iter() {
+            unsafe {
+                // keep può distruggere un altro network, i cui nodi aggiungono allora questo nodo ai dead_links. Per
+                // questo gli archi morti vengono copiati e tutti i verdetti raccolti prima di modificare il nodo: mentre
+                // keep è in esecuzione sono in prestito solo le mappe degli archi, non dead_links
+                let dead: HashSet<_> = (*(*ptr).dead_links.get()).iter().copied().collect();
+
+                let parallel: Vec<(*mut Node<T, G>, Vec<bool>)> = (*ptr).parallel.iter()
+                    .filter(|(dest, _)| !dead.contains(*dest))
+                    .map(|(&dest, costs)| (dest, costs.iter().map(&keep).collect()))
+                    .collect();
+                let dropped: Vec<_> = (*ptr).links.iter()
+                    .filter(|(dest, cost)| !dead.contains(*dest) && !keep(cost))
+                    .map(|(&dest, _)| dest)
+                    .collect();
+
+                // gli archi verso i network distrutti da keep non esistono più e vengono lasciati come archi morti
+                let node = &mut *ptr;
+
+                for (dest, verdicts) in parallel {
+                    if node.is_dead(dest) {
+                        continue;
+                    }
+
+                    let costs = node.parallel.get_mut(&dest).unwrap();
+                    let mut verdicts = verdicts.into_iter();
+                    let before = costs.len();
+                    costs.retain(|_| verdicts.next().unwrap());
+                    removed += before - costs.len();
+
+                    if costs.is_empty() {
+                        node.parallel.remove(&dest);
+                    }
+                }
+
+                for dest in dropped {
+                    if !node.is_dead(dest) {
+                        node.remove_first_link(dest);
+                        removed += 1;
+                    }
+                }
+            }
+        }
+
+        removed
+    }
+
+    // tutti i nodi del network in ordine di allocazione, indipendentemente da come sono collegati. I NodeVisit restano
+    // in prestito dal token, quindi durante l'iterazione non possono essere aggiunti o modificati nodi
+    pub fn iter_nodes<'t>(&'t self, _token: &'t GgToken<'id>) -> impl Iterator<Item = &'t NodeVisit<T, G>> {
+        let mut i = 0;
+
+        std::iter::from_fn(move || {
+            let ptr = *self.index.borrow().get(i)?;
+            i += 1;
+            unsafe { Some(NodeVisit::wrap(&(*ptr).this)) }
+        })
+    }
+
+    // tutti gli archi del network come triple (sorgente, destinazione, costo), scorrendo i nodi in ordine di
+    // allocazione e per ogni nodo i suoi archi uscenti. L'ordine degli archi di uno stesso nodo non è specificato ma
+    // non cambia finché il network non viene modificato. Gli archi paralleli creati con link_multi seguono quelli di links
+    pub fn iter_edges<'t>(&'t self, token: &'t GgToken<'id>) -> impl Iterator<Item = (NodeId, NodeId, &'t G)> {
+        self.iter_nodes(token).flat_map(|node| unsafe {
+            let parallel = (*node.ptr).parallel.iter().flat_map(|(dest, costs)| costs.iter().map(move |cost| (dest, cost)));
+
+            (*node.ptr).links.iter()
+                .chain(parallel)
+                .filter(move |(&dest, _)| !(*node.ptr).hides(dest))
+                .map(move |(&dest, cost)| (node.id(), NodeId(dest as usize), cost))
+        })
+    }
+
+    pub fn visit<R>(
+        &self,
+        root: &NodeRef<'_, 'id, '_, T, G>,
+        token: &GgToken<'id>,
+        mut each: impl FnMut(&NodeVisit<T, G>) -> R,
+    ) -> R {
+        each(root.as_visit(token))
+    }
+
+    // come visit ma la chiusura riceve il nodo in forma mutabile e può modificarne il valore tramite value_mut.
+    // La radice e il token sono presi in prestito mutabile, così durante la visita il valore non è accessibile né
+    // tramite il NodeRef né tramite visite e iteratori sul network
+    pub fn visit_mut<R>(
+        &self,
+        root: &mut NodeRef<'_, 'id, '_, T, G>,
+        _token: &mut GgToken<'id>,
+        mut each: impl FnMut(&mut NodeVisit<T, G>) -> R,
+    ) -> R {
+        each(&mut NodeVisit { ptr: root.ptr })
+    }
+
+    pub fn visit_multiple<R>(
+        &self,
+        roots: Vec<&NodeRef<'_, 'id, '_, T, G>>,
+        token: &GgToken<'id>,
+        mut each: impl FnMut(Vec<&NodeVisit<T, G>>) -> R,
+    ) -> R {
+        each(roots.into_iter().map(|root| root.as_visit(token)).collect())
+    }
+
+    // come visit, ma invece di passare alla chiusura solo root visita tutti i nodi raggiungibili da root (in ampiezza,
+    // come bfs) e chiama la chiusura al massimo una volta per nodo: i nodi già visitati sono riconosciuti dal loro
+    // indirizzo, quindi i cicli non vanno gestiti dalla chiusura
+    pub fn visit_unique(
+        &self,
+        root: &NodeRef<'_, 'id, '_, T, G>,
+        token: &GgToken<'id>,
+        mut each: impl FnMut(&NodeVisit<T, G>),
+    ) {
+        self.bfs(root, token, |node, _| each(node));
+    }
+
+    // versione parallela di visit_multiple: la visita in profondità di ogni radice viene eseguita sul thread pool di
+    // rayon e la chiusura viene chiamata per ogni nodo raggiungibile. Ogni radice usa un proprio insieme di nodi visitati,
+    // quindi i nodi raggiungibili da più radici vengono passati alla chiusura più volte (anche contemporaneamente da
+    // thread diversi). Né i NodeRef né il network sono Sync, quindi i thread del pool ricevono solo gli indirizzi delle
+    // radici e non usano mai self: durante la visita il token è in prestito, quindi nessun nodo può essere modificato
+    #[cfg(feature = "rayon")]
+    pub fn par_visit_multiple(
+        &self,
+        roots: Vec<&NodeRef<'_, 'id, '_, T, G>>,
+        _token: &GgToken<'id>,
+        each: impl Fn(&NodeVisit<T, G>) + Sync,
+    )
+    where
+        T: Sync,
+        G: Sync,
+    {
+        // i puntatori non sono Send, quindi ai thread vengono passati gli indirizzi
+        let roots: Vec<usize> = roots.iter().map(|root| root.ptr as usize).collect();
+
+        roots.par_iter().for_each(|&root| Self::dfs_from(root as *mut Node<T, G>, &each));
+    }
+
+    // versione parallela di un fold su iter_edges: i nodi vengono divisi tra i thread del pool di rayon, ogni thread
+    // parte da identity() e accumula con fold gli archi (sorgente, destinazione, costo) dei propri nodi, poi i risultati
+    // parziali vengono uniti con combine. Le chiusure vengono eseguite contemporaneamente da thread diversi, quindi
+    // devono essere Sync e non devono dipendere dall'ordine degli archi: combine deve essere associativa e identity()
+    // il suo elemento neutro, altrimenti il risultato cambia da un'esecuzione all'altra. Durante il fold il network non
+    // può essere modificato perché il token è preso in prestito, quindi serve solo che G sia Sync
+    #[cfg(feature = "rayon")]
+    pub fn par_edge_fold<A: Send>(
+        &self,
+        _token: &GgToken<'id>,
+        identity: impl Fn() -> A + Sync + Send,
+        fold: impl Fn(A, NodeId, NodeId, &G) -> A + Sync + Send,
+        combine: impl Fn(A, A) -> A + Sync + Send,
+    ) -> A
+    where
+        G: Sync,
+    {
+        // i puntatori non sono Send, quindi ai thread vengono passati gli indirizzi
+        let nodes: Vec<usize> = self.index.borrow().iter().map(|&ptr| ptr as usize).collect();
+
+        nodes.par_iter()
+            .fold(&identity, |acc, &address| unsafe {
+                let node = &*(address as *const Node<T, G>);
+                let parallel = node.parallel.iter().flat_map(|(dest, costs)| costs.iter().map(move |cost| (dest, cost)));
+
+                node.links.iter()
+                    .chain(parallel)
+                    .filter(|(&dest, _)| !node.hides(dest))
+                    .fold(acc, |acc, (&dest, cost)| fold(acc, NodeId(address), NodeId(dest as usize), cost))
+            })
+            .reduce(&identity, &combine)
+    }
+
+    // visita in ampiezza a partire da root: la chiusura riceve ogni nodo raggiungibile e la sua distanza (in archi)
+    // dalla radice. I nodi già visitati sono riconosciuti dal loro indirizzo, lo stesso usato da NodeVisit::hash,
+    // quindi i cicli (anche quelli creati con link_self) non causano loop infiniti. L'ordine in cui vengono
+    // visitati i nodi alla stessa profondità non è specificato.
+    pub fn bfs(
+        &self,
+        root: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+        mut each: impl FnMut(&NodeVisit<T, G>, usize),
+    ) {
+        Self::bfs_from(root.ptr, |ptr, depth| each(&NodeVisit { ptr }, depth));
+    }
+
+    // corpo di bfs: passa a each gli indirizzi dei nodi, così gli algoritmi che non leggono valori e costi possono
+    // visitare il network senza il token
+    fn bfs_from(root: *mut Node<T, G>, mut each: impl FnMut(*mut Node<T, G>, usize)) {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        visited.insert(root as usize);
+        queue.push_back((root, 0));
+
+        while let Some((ptr, depth)) = queue.pop_front() {
+            each(ptr, depth);
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if !(*ptr).skips(next) && visited.insert(next as usize) {
+                        queue.push_back((next, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    // visita in profondità (pre-order) a partire da root. La visita usa uno stack esplicito invece della ricorsione
+    // così da non esaurire lo stack del thread su catene molto lunghe, e riconosce i nodi già visitati dal loro indirizzo
+    pub fn dfs(&self, root: &NodeRef<'_, 'id, '_, T, G>, _token: &GgToken<'id>, each: impl FnMut(&NodeVisit<T, G>)) {
+        Self::dfs_from(root.ptr, each)
+    }
+
+    // corpo di dfs: legge solo gli archi dei nodi raggiungibili da root e non tocca l'indice del network, quindi
+    // può essere eseguito da più thread contemporaneamente senza un riferimento al network
+    fn dfs_from(root: *mut Node<T, G>, mut each: impl FnMut(&NodeVisit<T, G>)) {
+        let mut visited = HashSet::new();
+        let mut stack = vec![root];
+
+        while let Some(ptr) = stack.pop() {
+            if !visited.insert(ptr as usize) {
+                continue;
+            }
+
+            each(&NodeVisit { ptr });
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if !(*ptr).skips(next) && !visited.contains(&(next as usize)) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+    }
+
+    // come dfs ma in post-order: un nodo viene passato alla chiusura solo dopo tutti i nodi raggiungibili da esso
+    // (a meno di cicli), quindi è l'ordine adatto per risolvere dipendenze
+    pub fn dfs_post_order(
+        &self,
+        root: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+        mut each: impl FnMut(&NodeVisit<T, G>),
+    ) {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(root.ptr, false)];
+
+        while let Some((ptr, expanded)) = stack.pop() {
+            if expanded {
+                each(&NodeVisit { ptr });
+                continue;
+            }
+
+            if !visited.insert(ptr as usize) {
+                continue;
+            }
+
+            stack.push((ptr, true));
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if !(*ptr).skips(next) && !visited.contains(&(next as usize)) {
+                        stack.push((next, false));
+                    }
+                }
+            }
+        }
+    }
+
+    // tutti i nodi di questo network raggiungibili da node seguendo gli archi nella loro direzione, node compreso
+    pub fn reachable_from(&self, node: &NodeRef<'_, 'id, '_, T, G>) -> HashSet<NodeId> {
+        let mut reachable = HashSet::new();
+        Self::bfs_from(node.ptr, |ptr, _| { reachable.insert(NodeId(ptr as usize)); });
+        reachable
+    }
+
+    // come reachable_from ma solo i nodi a distanza (in archi) al massimo hops da start, ad esempio per trovare i vicini
+    // dei vicini senza visitare tutto il network. La bfs non espande i nodi a distanza hops, con hops == 0 il risultato
+    // contiene solo start
+    pub fn reachable_within(&self, start: &NodeRef<'_, 'id, '_, T, G>, hops: usize) -> HashSet<NodeId> {
+        let mut reachable = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        reachable.insert(start.id());
+        queue.push_back((start.ptr, 0));
+
+        while let Some((ptr, depth)) = queue.pop_front() {
+            if depth == hops {
+                continue;
+            }
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if !(*ptr).skips(next) && reachable.insert(NodeId(next as usize)) {
+                        queue.push_back((next, depth + 1));
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    // tutti i nodi raggiungibili da start con un costo totale (somma dei costi degli archi a partire da G::default())
+    // non superiore a budget, start compreso. L'espansione è quella di Dijkstra, che si ferma su ogni cammino appena il
+    // costo supera budget; come in shortest_path i costi negativi non sono supportati e gli archi con costo minore di
+    // G::default() vengono ignorati
+    pub fn reachable_within_cost(
+        &self,
+        start: &NodeRef<'_, 'id, '_, T, G>,
+        budget: G,
+        _token: &GgToken<'id>,
+    ) -> HashSet<NodeId>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let zero = G::default();
+        let mut dist = HashMap::new();
+        let mut reachable = HashSet::new();
+        let mut heap = BinaryHeap::new();
+
+        if zero > budget {
+            return reachable;
+        }
+
+        dist.insert(start.ptr as usize, zero.clone());
+        heap.push(Reverse((zero.clone(), start.ptr as usize)));
+
+        while let Some(Reverse((cost, id))) = heap.pop() {
+            if !reachable.insert(NodeId(id)) {
+                continue;
+            }
+
+            unsafe {
+                for (&next, weight) in (*(id as *mut Node<T, G>)).live_edges() {
+                    if *weight < zero || (*(id as *mut Node<T, G>)).skips(next) {
+                        continue;
+                    }
+
+                    let next_cost = cost.clone() + weight.clone();
+
+                    if next_cost <= budget && dist.get(&(next as usize)).is_none_or(|d| next_cost < *d) {
+                        dist.insert(next as usize, next_cost.clone());
+                        heap.push(Reverse((next_cost, next as usize)));
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    // massima distanza (in archi, seguendo la loro direzione) da node a un altro nodo del network, None se qualche nodo
+    // non è raggiungibile da node. Costa una bfs, O(V + E)
+    pub fn eccentricity(&self, node: &NodeRef<'_, 'id, '_, T, G>) -> Option<usize> {
+        let (_, positions) = self.positions();
+        Self::eccentricity_from(node.ptr, &positions)
+    }
+
+    // massima eccentricità tra i nodi del network, cioè il più lungo tra i cammini minimi (in archi) tra due nodi.
+    // None se il network è vuoto o se esiste una coppia di nodi in cui il secondo non è raggiungibile dal primo.
+    // Viene eseguita una bfs da ogni nodo, quindi il costo è O(V * (V + E)) e va usato solo su grafi di dimensioni modeste
+    pub fn diameter(&self) -> Option<usize> {
+        let (nodes, positions) = self.positions();
+        let mut diameter = None;
+
+        for &ptr in nodes.iter() {
+            let eccentricity = Self::eccentricity_from(ptr, &positions)?;
+            diameter = diameter.max(Some(eccentricity));
+        }
+
+        diameter
+    }
+
+    // bfs da root limitata ai nodi in positions: None se non li raggiunge tutti, altrimenti la profondità massima
+    fn eccentricity_from(root: *mut Node<T, G>, positions: &HashMap<*mut Node<T, G>, usize>) -> Option<usize> {
+        let mut distance = vec![None; positions.len()];
+        let mut queue = VecDeque::from([(root, 0)]);
+        let mut reached = 1;
+        let mut farthest = 0;
+        distance[positions[&root]] = Some(0);
+
+        while let Some((ptr, depth)) = queue.pop_front() {
+            farthest = depth;
+
+            unsafe {
+                for next in (*ptr).live_keys() {
+                    if let Some(&j) = positions.get(next) {
+                        if distance[j].is_none() {
+                            distance[j] = Some(depth + 1);
+                            reached += 1;
+                            queue.push_back((*next, depth + 1));
+                        }
+                    }
+                }
+            }
+        }
+
+        if reached == positions.len() {
+            Some(farthest)
+        } else {
+            None
+        }
+    }
+
+    // un cammino qualsiasi (non necessariamente minimo) da from a to trovato con una visita in profondità,
+    // None se to non è raggiungibile da from
+    pub fn find_path(&self, from: &NodeRef<'_, 'id, '_, T, G>, to: &NodeRef<'_, 'id, '_, T, G>) -> Option<Vec<NodeId>> {
+        let mut prev = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![from.ptr];
+
+        visited.insert(from.ptr as usize);
+
+        while let Some(ptr) = stack.pop() {
+            if ptr == to.ptr {
+                let mut path = vec![NodeId(ptr as usize)];
+                let mut current = ptr as usize;
+
+                while let Some(&p) = prev.get(&current) {
+                    path.push(NodeId(p));
+                    current = p;
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if !(*ptr).skips(next) && visited.insert(next as usize) {
+                        prev.insert(next as usize, ptr as usize);
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // cammino minimo da from a to con l'algoritmo di Dijkstra usando come costo i pesi degli archi. Il cammino è
+    // restituito come sequenza dei nodi (from e to compresi) insieme al costo totale, il costo
+    // di partenza è G::default(). Dijkstra non è corretto con pesi negativi, quindi gli archi con costo minore di
+    // G::default() vengono ignorati. Equivale ad astar con un'euristica sempre nulla.
+    pub fn shortest_path(
+        &self,
+        from: &NodeRef<'_, 'id, '_, T, G>,
+        to: &NodeRef<'_, 'id, '_, T, G>,
+        token: &GgToken<'id>,
+    ) -> Option<(Vec<NodeId>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        self.astar(from, to, token, |_| G::default())
+    }
+
+    // come shortest_path ma il cammino è restituito come sequenza degli archi percorsi (sorgente, destinazione, costo),
+    // così il costo di ogni tratto è disponibile senza interrogare di nuovo gli archi. Se from e to coincidono la
+    // sequenza è vuota
+    pub fn shortest_path_edges(
+        &self,
+        from: &NodeRef<'_, 'id, '_, T, G>,
+        to: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+    ) -> Option<Vec<(NodeId, NodeId, G)>>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let (path, _) = self.search(from.ptr, to.ptr, |_| G::default(), |_, _| true)?;
+
+        let edges = path
+            .windows(2)
+            .map(|hop| {
+                let cost = unsafe { (*hop[0]).cheapest_to(hop[1]).unwrap().clone() };
+                (NodeId(hop[0] as usize), NodeId(hop[1] as usize), cost)
+            })
+            .collect();
+
+        Some(edges)
+    }
+
+    // albero dei cammini minimi da source calcolato con una sola esecuzione di Dijkstra: per ogni nodo raggiungibile la
+    // distanza da source e il predecessore sul cammino minimo (None per source, la cui distanza è G::default()), così
+    // il cammino verso qualsiasi nodo si ricostruisce risalendo i predecessori. Come in shortest_path gli archi con
+    // costo minore di G::default() vengono ignorati
+    pub fn dijkstra_tree(
+        &self,
+        source: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+    ) -> HashMap<NodeId, (G, Option<NodeId>)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let (dist, prev, _) = self.explore(source.ptr, None, |_| G::default(), |_, _| true);
+
+        dist.into_iter()
+            .map(|(id, cost)| (NodeId(id), (cost, prev.get(&id).map(|&p| NodeId(p)))))
+            .collect()
+    }
+
+    // come shortest_path ma i nodi per cui avoid restituisce true non vengono mai attraversati, ad esempio per
+    // instradare attorno a nodi guasti o congestionati senza modificare il network. Se from o to sono da evitare non
+    // esiste nessun cammino e viene restituito None
+    pub fn shortest_path_avoiding(
+        &self,
+        from: &NodeRef<'_, 'id, '_, T, G>,
+        to: &NodeRef<'_, 'id, '_, T, G>,
+        token: &GgToken<'id>,
+        avoid: impl Fn(&NodeVisit<T, G>) -> bool,
+    ) -> Option<(Vec<NodeId>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        if avoid(from.as_visit(token)) || avoid(to.as_visit(token)) {
+            return None;
+        }
+
+        self.search(from.ptr, to.ptr, |_| G::default(), |_, next| !avoid(&NodeVisit { ptr: next }))
+            .map(|(path, cost)| (path.into_iter().map(|ptr| NodeId(ptr as usize)).collect(), cost))
+    }
+
+    // cammino minimo da start a goal con l'algoritmo A*: come shortest_path, ma i nodi vengono espansi in ordine di
+    // costo accumulato più la stima heuristic del costo rimanente fino a goal. Il risultato è ottimo solo se
+    // l'euristica è ammissibile, cioè non sovrastima mai il costo reale per raggiungere goal. Come in Dijkstra gli
+    // archi con costo minore di G::default() vengono ignorati.
+    pub fn astar(
+        &self,
+        start: &NodeRef<'_, 'id, '_, T, G>,
+        goal: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+        heuristic: impl Fn(&NodeVisit<T, G>) -> G,
+    ) -> Option<(Vec<NodeId>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        self.search(start.ptr, goal.ptr, heuristic, |_, _| true)
+            .map(|(path, cost)| (path.into_iter().map(|ptr| NodeId(ptr as usize)).collect(), cost))
+    }
+
+    // i k cammini senza cicli più brevi da from a to in ordine di costo crescente, con l'algoritmo di Yen. Ogni cammino
+    // successivo al primo viene cercato deviando da un nodo (spur) di un cammino già trovato: per farlo gli archi e i
+    // nodi da evitare non vengono rimossi dal network ma semplicemente scartati durante la ricerca, quindi il network
+    // non viene mai modificato. Se esistono meno di k cammini vengono restituiti tutti quelli trovati
+    pub fn k_shortest_paths(
+        &self,
+        from: &NodeRef<'_, 'id, '_, T, G>,
+        to: &NodeRef<'_, 'id, '_, T, G>,
+        k: usize,
+        _token: &GgToken<'id>,
+    ) -> Vec<(Vec<NodeId>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let mut found: Vec<(Vec<*mut Node<T, G>>, G)> = Vec::new();
+        let mut candidates: Vec<(Vec<*mut Node<T, G>>, G)> = Vec::new();
+
+        if k > 0 {
+            found.extend(self.search(from.ptr, to.ptr, |_| G::default(), |_, _| true));
+        }
+
+        while found.len() < k && !found.is_empty() {
+            let last = found[found.len() - 1].0.clone();
+            let mut root_cost = G::default();
+
+            for i in 0..last.len() - 1 {
+                let spur = last[i];
+                let root = &last[..=i];
+                let banned_nodes: HashSet<_> = root[..i].iter().copied().collect();
+                let banned_edges: HashSet<_> = found.iter()
+                    .filter(|(path, _)| path.len() > i + 1 && path[..=i] == *root)
+                    .map(|(path, _)| (path[i], path[i + 1]))
+                    .collect();
+
+                let spur_path = self.search(spur, to.ptr, |_| G::default(), |source, dest| {
+                    !banned_nodes.contains(&dest) && !banned_edges.contains(&(source, dest))
+                });
+
+                if let Some((spur_path, spur_cost)) = spur_path {
+                    let mut path = root[..i].to_vec();
+                    path.extend(spur_path);
+
+                    if !found.iter().chain(candidates.iter()).any(|(other, _)| *other == path) {
+                        candidates.push((path, root_cost.clone() + spur_cost));
+                    }
+                }
+
+                unsafe {
+                    root_cost = root_cost + (*spur).cheapest_to(last[i + 1]).cloned().unwrap_or_default();
+                }
+            }
+
+            // a parità di costo viene scelto il candidato trovato per primo
+            let best = candidates.iter()
+                .enumerate()
+                .min_by(|(i, a), (j, b)| a.1.cmp(&b.1).then(i.cmp(j)))
+                .map(|(i, _)| i);
+
+            match best {
+                Some(i) => found.push(candidates.remove(i)),
+                None => break,
+            }
+        }
+
+        found.into_iter()
+            .map(|(path, cost)| (path.into_iter().map(|ptr| NodeId(ptr as usize)).collect(), cost))
+            .collect()
+    }
+
+    // ricerca A* usata da astar e dagli altri cammini minimi: allow filtra gli archi (sorgente, destinazione) che
+    // possono essere percorsi, così gli algoritmi che devono evitare archi o nodi non devono modificare il network.
+    // Il cammino è restituito come sequenza di indirizzi dei nodi
+    fn search(
+        &self,
+        start: *mut Node<T, G>,
+        goal: *mut Node<T, G>,
+        heuristic: impl Fn(&NodeVisit<T, G>) -> G,
+        allow: impl Fn(*mut Node<T, G>, *mut Node<T, G>) -> bool,
+    ) -> Option<(Vec<*mut Node<T, G>>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let (_, prev, cost) = self.explore(start, Some(goal), heuristic, allow);
+        let cost = cost?;
+        let mut path = vec![goal];
+        let mut current = goal as usize;
+
+        while let Some(&p) = prev.get(&current) {
+            path.push(p as *mut Node<T, G>);
+            current = p;
+        }
+
+        path.reverse();
+        Some((path, cost))
+    }
+
+    // nucleo di search: espande i nodi in ordine di costo più euristica fino a raggiungere goal, o tutti i nodi
+    // raggiungibili se goal è None. Restituisce le distanze e i predecessori trovati (indicizzati per indirizzo) e il
+    // costo di goal se è stato raggiunto. Senza goal e con euristica nulla le distanze sono quelle minime di Dijkstra
+    fn explore(
+        &self,
+        start: *mut Node<T, G>,
+        goal: Option<*mut Node<T, G>>,
+        heuristic: impl Fn(&NodeVisit<T, G>) -> G,
+        allow: impl Fn(*mut Node<T, G>, *mut Node<T, G>) -> bool,
+    ) -> (HashMap<usize, G>, HashMap<usize, usize>, Option<G>)
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let zero = G::default();
+        let mut dist = HashMap::new();
+        let mut prev = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        let estimate = |ptr: *mut Node<T, G>, cost: &G| cost.clone() + heuristic(&NodeVisit { ptr });
+
+        dist.insert(start as usize, zero.clone());
+        heap.push(Reverse((estimate(start, &zero), zero.clone(), start as usize)));
+
+        while let Some(Reverse((_, cost, id))) = heap.pop() {
+            if goal.is_some_and(|goal| id == goal as usize) {
+                return (dist, prev, Some(cost));
+            }
+
+            if dist.get(&id).is_some_and(|d| cost > *d) {
+                continue;
+            }
+
+            unsafe {
+                for (&next, weight) in (*(id as *mut Node<T, G>)).live_edges() {
+                    if *weight < zero || (*(id as *mut Node<T, G>)).skips(next) || !allow(id as *mut Node<T, G>, next) {
+                        continue;
+                    }
+
+                    let next_cost = cost.clone() + weight.clone();
+
+                    if dist.get(&(next as usize)).is_none_or(|d| next_cost < *d) {
+                        dist.insert(next as usize, next_cost.clone());
+                        prev.insert(next as usize, id);
+                        heap.push(Reverse((estimate(next, &next_cost), next_cost, next as usize)));
+                    }
+                }
+            }
+        }
+
+        (dist, prev, None)
+    }
+
+    // distanza minima da source a ogni nodo raggiungibile con l'algoritmo di Bellman-Ford, che a differenza di
+    // shortest_path accetta archi di costo negativo. La distanza di source è G::default(). Se dalla sorgente è
+    // raggiungibile un ciclo di costo negativo le distanze non sono definite e viene restituito un NegativeCycleError
+    pub fn bellman_ford(
+        &self,
+        source: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+    ) -> Result<HashMap<NodeId, G>, NegativeCycleError>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let mut reachable = Vec::new();
+        Self::bfs_from(source.ptr, |ptr, _| reachable.push(ptr));
+
+        let mut dist = HashMap::new();
+        dist.insert(source.ptr as usize, G::default());
+
+        // dopo |V| - 1 passate tutte le distanze sono minime, a meno di cicli negativi
+        for _ in 1..reachable.len() {
+            let mut changed = false;
+
+            for &ptr in reachable.iter() {
+                let cost = match dist.get(&(ptr as usize)) {
+                    Some(cost) => cost.clone(),
+                    None => continue,
+                };
+
+                unsafe {
+                    for (&next, weight) in (*ptr).live_edges() {
+                        if (*ptr).skips(next) {
+                            continue;
+                        }
+
+                        let next_cost = cost.clone() + weight.clone();
+
+                        if dist.get(&(next as usize)).is_none_or(|d| next_cost < *d) {
+                            dist.insert(next as usize, next_cost);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for &ptr in reachable.iter() {
+            let cost = dist[&(ptr as usize)].clone();
+
+            unsafe {
+                for (&next, weight) in (*ptr).live_edges() {
+                    if (*ptr).skips(next) {
+                        continue;
+                    }
+
+                    if cost.clone() + weight.clone() < dist[&(next as usize)] {
+                        return Err(NegativeCycleError { node: NodeId(next as usize) });
+                    }
+                }
+            }
+        }
+
+        Ok(dist.into_iter().map(|(id, cost)| (NodeId(id), cost)).collect())
+    }
+
+    // distanze minime tra tutte le coppie di nodi del network con l'algoritmo di Floyd-Warshall. La distanza di un nodo
+    // da sé stesso parte da G::default() e le coppie non raggiungibili non compaiono nella mappa. Il costo è O(V^3)
+    // in tempo e O(V^2) in memoria, quindi va usato solo su grafi piccoli. Gli archi verso altri network vengono ignorati
+    pub fn all_pairs_shortest_paths(&self, _token: &GgToken<'id>) -> HashMap<(NodeId, NodeId), G>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let (nodes, positions) = self.positions();
+        let n = nodes.len();
+        let mut dist: Vec<Vec<Option<G>>> = vec![vec![None; n]; n];
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            dist[i][i] = Some(G::default());
+
+            unsafe {
+                for (next, weight) in (*ptr).live_edges() {
+                    if let Some(&j) = positions.get(next) {
+                        if dist[i][j].as_ref().is_none_or(|d| weight < d) {
+                            dist[i][j] = Some(weight.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        for k in 0..n {
+            // la riga k viene copiata perché le righe i vengono modificate mentre la si legge: passare da k non può
+            // accorciare i cammini che partono da k, quindi la copia resta uguale alla riga per tutto il passo
+            let from_k = dist[k].clone();
+
+            for row in dist.iter_mut() {
+                let through = match &row[k] {
+                    Some(d) => d.clone(),
+                    None => continue,
+                };
+
+                for (current, rest) in row.iter_mut().zip(from_k.iter()) {
+                    if let Some(rest) = rest {
+                        let candidate = through.clone() + rest.clone();
+
+                        if current.as_ref().is_none_or(|d| candidate < *d) {
+                            *current = Some(candidate);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut result = HashMap::new();
+
+        for (i, row) in dist.into_iter().enumerate() {
+            for (j, d) in row.into_iter().enumerate() {
+                if let Some(d) = d {
+                    result.insert((NodeId(nodes[i] as usize), NodeId(nodes[j] as usize)), d);
+                }
+            }
+        }
+
+        result
+    }
+
+    // flusso massimo da source a sink con l'algoritmo di Edmonds-Karp, interpretando il costo di ogni arco come la sua
+    // capacità (archi con capacità minore di G::default(), cappi e archi verso altri network vengono ignorati).
+    // Gli archi paralleli verso la stessa destinazione sommano le loro capacità, quindi il network residuo è tenuto a
+    // parte come una mappa destinazione -> capacità residua per ogni nodo: l'arco all'indietro j -> i di un arco i -> j
+    // è la stessa voce della mappa di j, che parte dalla capacità degli eventuali archi reali j -> i (o da
+    // G::default()) e cresce del flusso spinto su i -> j. Il network non viene modificato. Se source e sink coincidono
+    // il flusso è G::default()
+    pub fn max_flow(
+        &self,
+        source: &NodeRef<'_, 'id, '_, T, G>,
+        sink: &NodeRef<'_, 'id, '_, T, G>,
+        _token: &GgToken<'id>,
+    ) -> G
+    where
+        G: Ord + Clone + Add<Output = G> + Sub<Output = G> + Default,
+    {
+        let (nodes, positions) = self.positions();
+        let (s, t) = (positions[&source.ptr], positions[&sink.ptr]);
+        let mut residual: Vec<HashMap<usize, G>> = vec![HashMap::new(); nodes.len()];
+        let mut flow = G::default();
+
+        if s == t {
+            return flow;
+        }
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for (next, capacity) in (*ptr).live_edges() {
+                    if let Some(&j) = positions.get(next) {
+                        if i != j && *capacity > G::default() {
+                            // le capacità degli archi paralleli si sommano
+                            let total = residual[i].entry(j).or_default();
+                            *total = total.clone() + capacity.clone();
+                            residual[j].entry(i).or_default();
+                        }
+                    }
+                }
+            }
+        }
+
+        loop {
+            // bfs sul network residuo: parent[j] è il nodo da cui j è stato raggiunto
+            let mut parent = vec![None; nodes.len()];
+            let mut queue = VecDeque::from([s]);
+            parent[s] = Some(s);
+
+            while let Some(i) = queue.pop_front() {
+                if i == t {
+                    break;
+                }
+
+                for (&j, capacity) in residual[i].iter() {
+                    if parent[j].is_none() && *capacity > G::default() {
+                        parent[j] = Some(i);
+                        queue.push_back(j);
+                    }
+                }
+            }
+
+            if parent[t].is_none() {
+                return flow;
+            }
+
+            let mut bottleneck: Option<G> = None;
+            let mut j = t;
+
+            while j != s {
+                let i = parent[j].unwrap();
+                let capacity = &residual[i][&j];
+
+                if bottleneck.as_ref().is_none_or(|b| capacity < b) {
+                    bottleneck = Some(capacity.clone());
+                }
+
+                j = i;
+            }
+
+            let bottleneck = bottleneck.unwrap();
+            let mut j = t;
+
+            while j != s {
+                let i = parent[j].unwrap();
+                let forward = residual[i].get_mut(&j).unwrap();
+                *forward = forward.clone() - bottleneck.clone();
+                let backward = residual[j].get_mut(&i).unwrap();
+                *backward = backward.clone() + bottleneck.clone();
+                j = i;
+            }
+
+            flow = flow + bottleneck;
+        }
+    }
+
+    // ordinamento topologico dei nodi raggiungibili da root: ogni nodo compare prima di tutti i nodi verso cui ha
+    // un arco, quindi per un grafo di dipendenze (a -> b se a dipende da b) le dipendenze si trovano invertendo
+    // il risultato. Se viene trovato un ciclo (anche un cappio creato con link_self) viene restituito un CycleError.
+    // La visita è una dfs a tre colori con stack esplicito: un arco verso un nodo ancora in corso di visita chiude un ciclo.
+    pub fn topological_sort(&self, root: &NodeRef<'_, 'id, '_, T, G>) -> Result<Vec<NodeId>, CycleError> {
+        // false: nodo in corso di visita, true: nodo completato
+        let mut state = HashMap::new();
+        let mut order = Vec::new();
+        let mut stack = vec![(root.ptr, false)];
+
+        while let Some((ptr, expanded)) = stack.pop() {
+            if expanded {
+                state.insert(ptr as usize, true);
+                order.push(NodeId(ptr as usize));
+                continue;
+            }
+
+            if state.contains_key(&(ptr as usize)) {
+                continue;
+            }
+
+            state.insert(ptr as usize, false);
+            stack.push((ptr, true));
+
+            unsafe {
+                for &next in (*ptr).live_keys() {
+                    if (*ptr).skips(next) {
+                        continue;
+                    }
+
+                    match state.get(&(next as usize)) {
+                        Some(false) => return Err(CycleError { node: NodeId(next as usize) }),
+                        Some(true) => {}
+                        None => stack.push((next, false)),
+                    }
+                }
+            }
+        }
+
+        order.reverse();
+        Ok(order)
+    }
+
+    // tutti i nodi del network in ordine topologico, da usare con i normali adattatori degli iteratori. A differenza
+    // di topological_sort considera l'intero network e non solo i nodi raggiungibili da una radice: l'ordine è
+    // calcolato subito con l'algoritmo di Kahn (a parità di condizioni i nodi escono in ordine di allocazione), quindi
+    // un ciclo viene segnalato con un CycleError prima di restituire l'iteratore. Gli archi verso altri network vengono
+    // ignorati
+    pub fn iter_topological<'t>(
+        &'t self,
+        _token: &'t GgToken<'id>,
+    ) -> Result<impl Iterator<Item = &'t NodeVisit<T, G>>, CycleError> {
+        let order = self.kahn_order()?;
+        Ok(order.into_iter().map(|ptr| unsafe { NodeVisit::wrap(&(*ptr).this) }))
+    }
+
+    // cammino di costo massimo nel network, ad esempio il cammino critico di una pianificazione. Funziona solo su grafi
+    // aciclici: gli archi vengono rilassati nello stesso ordine topologico di iter_topological tenendo per ogni nodo il
+    // massimo costo accumulato (ogni nodo può essere l'inizio di un cammino con costo G::default()). Restituisce None se
+    // il network contiene un ciclo o è vuoto; a parità di costo viene scelto il cammino che termina nel primo nodo in
+    // ordine topologico. Gli archi verso altri network vengono ignorati
+    pub fn longest_path(&self, _token: &GgToken<'id>) -> Option<(Vec<NodeId>, G)>
+    where
+        G: Ord + Clone + Add<Output = G> + Default,
+    {
+        let order = self.kahn_order().ok()?;
+        let (_, positions) = self.positions();
+        let mut best: Vec<(G, Option<*mut Node<T, G>>)> = vec![(G::default(), None); order.len()];
+
+        for &ptr in order.iter() {
+            let cost = best[positions[&ptr]].0.clone();
+
+            unsafe {
+                for (next, weight) in (*ptr).live_edges() {
+                    if let Some(&j) = positions.get(next) {
+                        let candidate = cost.clone() + weight.clone();
+
+                        if candidate > best[j].0 {
+                            best[j] = (candidate, Some(ptr));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut end = *order.first()?;
+
+        for &ptr in order.iter() {
+            if best[positions[&ptr]].0 > best[positions[&end]].0 {
+                end = ptr;
+            }
+        }
+
+        let total = best[positions[&end]].0.clone();
+        let mut path = vec![NodeId(end as usize)];
+        let mut current = end;
+
+        while let Some(prev) = best[positions[&current]].1 {
+            path.push(NodeId(prev as usize));
+            current = prev;
+        }
+
+        path.reverse();
+        Some((path, total))
+    }
+
+    // ordine topologico dell'intero network con l'algoritmo di Kahn, CycleError se il network contiene un ciclo
+    fn kahn_order(&self) -> Result<Vec<*mut Node<T, G>>, CycleError> {
+        let (nodes, adjacency) = self.adjacency();
+        let mut in_degree = vec![0; nodes.len()];
+
+        for successors in adjacency.iter() {
+            for &j in successors.iter() {
+                in_degree[j] += 1;
+            }
+        }
+
+        let mut queue: VecDeque<usize> = (0..nodes.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut order = Vec::with_capacity(nodes.len());
+
+        while let Some(i) = queue.pop_front() {
+            order.push(nodes[i]);
+
+            for &j in adjacency[i].iter() {
+                in_degree[j] -= 1;
+
+                if in_degree[j] == 0 {
+                    queue.push_back(j);
+                }
+            }
+        }
+
+        // i nodi rimasti con archi entranti sono su un ciclo o raggiungibili da uno
+        if let Some(i) = (0..nodes.len()).find(|&i| in_degree[i] > 0) {
+            return Err(CycleError { node: NodeId(nodes[i] as usize) });
+        }
+
+        Ok(order)
+    }
+
+    // true se il network contiene almeno un ciclo, compresi i cappi creati con link_self. Come topological_sort
+    // usa una dfs a tre colori, ma partendo da ogni nodo del network così da coprire anche le componenti non connesse
+    pub fn has_cycle(&self) -> bool {
+        // false: nodo in corso di visita, true: nodo completato
+        let mut state = HashMap::new();
+        let roots = self.index.borrow().clone();
+
+        for root in roots {
+            if state.contains_key(&(root as usize)) {
+                continue;
+            }
+
+            let mut stack = vec![(root, false)];
+
+            while let Some((ptr, expanded)) = stack.pop() {
+                if expanded {
+                    state.insert(ptr as usize, true);
+                    continue;
+                }
+
+                if state.contains_key(&(ptr as usize)) {
+                    continue;
+                }
+
+                state.insert(ptr as usize, false);
+                stack.push((ptr, true));
+
+                unsafe {
+                    for &next in (*ptr).live_keys() {
+                        if (*ptr).skips(next) {
+                            continue;
+                        }
+
+                        match state.get(&(next as usize)) {
+                            Some(false) => return true,
+                            Some(true) => {}
+                            None => stack.push((next, false)),
+                        }
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    // componenti connesse del network considerando gli archi come non orientati. Ogni componente contiene i suoi
+    // nodi in ordine di allocazione e le componenti sono ordinate secondo il loro primo nodo. Gli archi verso nodi
+    // di altri network (link_outer) vengono ignorati
+    pub fn connected_components(&self) -> Vec<Vec<NodeId>> {
+        let (nodes, positions) = self.positions();
+        let mut sets = UnionFind::new(nodes.len());
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for next in (*ptr).live_keys() {
+                    if let Some(&j) = positions.get(next) {
+                        sets.union(i, j);
+                    }
+                }
+            }
+        }
+
+        let mut components: Vec<Vec<NodeId>> = Vec::new();
+        let mut component_of = HashMap::new();
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            let root = sets.find(i);
+            let c = *component_of.entry(root).or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[c].push(NodeId(ptr as usize));
+        }
+
+        components
+    }
+
+    // componenti debolmente connesse di un network orientato, cioè connesse ignorando la direzione degli archi.
+    // connected_components considera già gli archi come non orientati, quindi il risultato è lo stesso (anche
+    // nell'ordine); il nome esplicito serve a distinguerle dalle strongly_connected_components
+    pub fn weakly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        self.connected_components()
+    }
+
+    // per ogni nodo la posizione della sua componente in weakly_connected_components, ad esempio per dividere il
+    // network in parti indipendenti da elaborare in parallelo
+    pub fn weak_component_map(&self) -> HashMap<NodeId, usize> {
+        self.weakly_connected_components()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(c, members)| members.into_iter().map(move |id| (id, c)))
+            .collect()
+    }
+
+    // componenti fortemente connesse con l'algoritmo di Tarjan. Al posto della ricorsione viene usato uno stack
+    // esplicito di frame (nodo, successori, prossimo successore da visitare) così da non esaurire lo stack del thread.
+    // Un nodo che non fa parte di nessun ciclo forma una componente da solo. Le componenti sono restituite in ordine
+    // topologico inverso (una componente compare prima di quelle che hanno archi verso di essa) e gli archi verso nodi
+    // di altri network vengono ignorati
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        let nodes = self.index.borrow().clone();
+        let members: HashSet<_> = nodes.iter().copied().collect();
+        let successors = |ptr: *mut Node<T, G>| -> Vec<*mut Node<T, G>> {
+            unsafe { (*ptr).live_keys().copied().filter(|next| members.contains(next)).collect() }
+        };
+
+        let mut counter = 0;
+        let mut discovery = HashMap::new();
+        let mut low = HashMap::new();
+        let mut on_stack = HashSet::new();
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+
+        for root in nodes.iter().copied() {
+            if discovery.contains_key(&root) {
+                continue;
+            }
+
+            discovery.insert(root, counter);
+            low.insert(root, counter);
+            counter += 1;
+            stack.push(root);
+            on_stack.insert(root);
+            let mut frames = vec![(root, successors(root), 0)];
+
+            while let Some((ptr, next_nodes, position)) = frames.last_mut() {
+                let ptr = *ptr;
+
+                if *position < next_nodes.len() {
+                    let next = next_nodes[*position];
+                    *position += 1;
+
+                    if let Entry::Vacant(entry) = discovery.entry(next) {
+                        entry.insert(counter);
+                        low.insert(next, counter);
+                        counter += 1;
+                        stack.push(next);
+                        on_stack.insert(next);
+                        frames.push((next, successors(next), 0));
+                    } else if on_stack.contains(&next) {
+                        let value = low[&ptr].min(discovery[&next]);
+                        low.insert(ptr, value);
+                    }
+
+                    continue;
+                }
+
+                frames.pop();
+
+                if let Some((parent, _, _)) = frames.last() {
+                    let value = low[parent].min(low[&ptr]);
+                    low.insert(*parent, value);
+                }
+
+                if low[&ptr] == discovery[&ptr] {
+                    let mut component = Vec::new();
+
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(&member);
+                        component.push(NodeId(member as usize));
+
+                        if member == ptr {
+                            break;
+                        }
+                    }
+
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    // grafo condensato: in dest viene creato un nodo per ogni componente fortemente connessa (con valore la lista dei
+    // suoi nodi, nello stesso ordine di strongly_connected_components) e un arco tra due componenti se nel network
+    // esiste almeno un arco tra i loro nodi. Il risultato è sempre aciclico e quindi adatto a ordinamenti topologici.
+    // La mappa restituita associa ogni nodo di self al nodo di dest che rappresenta la sua componente
+    pub fn condensation<'b>(
+        &self,
+        dest: &GenerationalGraph<'b, Vec<NodeId>, ()>,
+        token: &mut GgToken<'b>,
+    ) -> HashMap<NodeId, NodeId> {
+        let components = self.strongly_connected_components();
+        let mut component_of = HashMap::new();
+        let mut supers = Vec::with_capacity(components.len());
+
+        for (c, members) in components.into_iter().enumerate() {
+            for &id in members.iter() {
+                component_of.insert(id.0 as *mut Node<T, G>, c);
+            }
+
+            supers.push(dest.add(members, token).ptr);
+        }
+
+        for (&ptr, &c) in component_of.iter() {
+            unsafe {
+                for next in (*ptr).live_keys() {
+                    if let Some(&d) = component_of.get(next) {
+                        if c != d {
+                            let source = supers[c];
+                            (*source).insert_link(supers[d], ());
+                        }
+                    }
+                }
+            }
+        }
+
+        component_of.into_iter().map(|(ptr, c)| (NodeId(ptr as usize), NodeId(supers[c] as usize))).collect()
+    }
+
+    // albero (o foresta, se il network non è connesso) di copertura minimo con l'algoritmo di Kruskal, considerando gli
+    // archi come non orientati: se esistono sia a -> b che b -> a vengono trattati come due candidati per la stessa
+    // coppia. I cappi e gli archi verso altri network vengono ignorati. Gli archi scelti sono restituiti in ordine di costo
+    pub fn minimum_spanning_tree(&self, _token: &GgToken<'id>) -> Vec<(NodeId, NodeId, G)>
+    where
+        G: Ord + Clone,
+    {
+        let (nodes, positions) = self.positions();
+        let mut edges = Vec::new();
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for (next, cost) in (*ptr).live_edges() {
+                    match positions.get(next) {
+                        Some(&j) if j != i => edges.push((i, j, cost)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // a parità di costo l'ordine di allocazione rende il risultato deterministico
+        edges.sort_by(|a, b| a.2.cmp(b.2).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+
+        let mut sets = UnionFind::new(nodes.len());
+        let mut tree = Vec::new();
+
+        for (i, j, cost) in edges {
+            if sets.union(i, j) {
+                tree.push((NodeId(nodes[i] as usize), NodeId(nodes[j] as usize), cost.clone()));
+            }
+        }
+
+        tree
+    }
+
+    // true se per ogni arco a -> b con costo c esiste anche l'arco b -> a con lo stesso costo, cioè se il network può
+    // essere trattato come non orientato (ad esempio perché costruito solo con link_undirected). Gli archi paralleli
+    // devono corrispondere uno a uno, quelli verso altri network vengono ignorati. Va controllato prima di usare
+    // algoritmi che considerano gli archi come non orientati su un network costruito anche con link
+    pub fn is_symmetric(&self, _token: &GgToken<'id>) -> bool
+    where
+        G: PartialEq,
+    {
+        let (nodes, positions) = self.positions();
+        // costi di tutti gli archi da from a to, il primo e quelli paralleli
+        let costs = |from: *mut Node<T, G>, to: *mut Node<T, G>| unsafe {
+            (*from).costs_to(to).collect::<Vec<_>>()
+        };
+
+        nodes.iter().all(|&ptr| unsafe {
+            (*ptr).live_keys().filter(|next| positions.contains_key(*next)).all(|&next| {
+                let (forward, mut backward) = (costs(ptr, next), costs(next, ptr));
+
+                forward.len() == backward.len() && forward.iter().all(|cost| match backward.iter().position(|other| other == cost) {
+                    Some(i) => {
+                        backward.swap_remove(i);
+                        true
+                    }
+                    None => false,
+                })
+            })
+        })
+    }
+
+    // true se i nodi del network possono essere divisi in due insiemi senza archi interni, considerando gli archi
+    // come non orientati. Un cappio rende il network non bipartito
+    pub fn is_bipartite(&self) -> bool {
+        self.bipartition().is_some()
+    }
+
+    // come is_bipartite ma restituisce anche i due insiemi. Ogni componente connessa viene colorata con una bfs
+    // a partire dal suo primo nodo in ordine di allocazione, che finisce sempre nel primo insieme
+    pub fn bipartition(&self) -> Option<(Vec<NodeId>, Vec<NodeId>)> {
+        let (nodes, adjacency) = self.undirected_adjacency();
+        let mut color = vec![None; nodes.len()];
+        let mut queue = VecDeque::new();
+
+        for start in 0..nodes.len() {
+            if color[start].is_some() {
+                continue;
+            }
+
+            color[start] = Some(false);
+            queue.push_back(start);
+
+            while let Some(i) = queue.pop_front() {
+                let side = color[i] == Some(true);
+
+                for &j in adjacency[i].iter() {
+                    match color[j] {
+                        None => {
+                            color[j] = Some(!side);
+                            queue.push_back(j);
+                        }
+                        Some(other) if other == side => return None,
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            if color[i] == Some(false) {
+                left.push(NodeId(ptr as usize));
+            } else {
+                right.push(NodeId(ptr as usize));
+            }
+        }
+
+        Some((left, right))
+    }
+
+    // colorazione greedy: ogni nodo, in ordine di allocazione, riceve il più piccolo colore non ancora usato dai suoi
+    // vicini (archi considerati come non orientati, cappi ignorati). Il risultato dipende dall'ordine dei nodi e non è
+    // in generale ottimo: il massimo colore assegnato più uno è solo un limite superiore al numero cromatico
+    pub fn greedy_color(&self) -> HashMap<NodeId, usize> {
+        let (nodes, adjacency) = self.undirected_adjacency();
+        let mut color: Vec<Option<usize>> = vec![None; nodes.len()];
+        let mut used = Vec::new();
+
+        for i in 0..nodes.len() {
+            used.clear();
+            used.resize(adjacency[i].len() + 1, false);
+
+            for &j in adjacency[i].iter() {
+                if let Some(c) = color[j] {
+                    if j != i && c < used.len() {
+                        used[c] = true;
+                    }
+                }
+            }
+
+            color[i] = used.iter().position(|&taken| !taken);
+        }
+
+        nodes
+            .iter()
+            .zip(color)
+            .map(|(&ptr, c)| (NodeId(ptr as usize), c.unwrap()))
+            .collect()
+    }
+
+    // copia il network in dest invertendo la direzione di ogni arco. Ogni nodo viene copiato in dest nello stesso ordine
+    // di allocazione, quindi l'i-esimo nodo di dest corrisponde all'i-esimo nodo di self; la mappa restituita associa
+    // l'identità di ogni nodo di self a quella della sua copia. Gli archi verso altri network non vengono copiati
+    pub fn transpose<'b>(
+        &self,
+        token: &GgToken<'id>,
+        dest: &GenerationalGraph<'b, T, G>,
+        dest_token: &mut GgToken<'b>,
+    ) -> HashMap<NodeId, NodeId>
+    where
+        T: Clone,
+        G: Clone,
+    {
+        self.copy_into(token, dest, dest_token, |_| true, true, G::clone)
+    }
+
+    // copia il network in dest, ad esempio per conservarne una versione prima di modificarlo. Come in transpose i nodi
+    // vengono copiati nello stesso ordine di allocazione e la mappa restituita associa ogni nodo di self alla sua copia;
+    // gli archi (anche quelli paralleli) collegano le copie e non i nodi originali, tranne quelli verso altri network
+    // che non vengono copiati
+    pub fn clone_into<'b>(
+        &self,
+        token: &GgToken<'id>,
+        dest: &GenerationalGraph<'b, T, G>,
+        dest_token: &mut GgToken<'b>,
+    ) -> HashMap<NodeId, NodeId>
+    where
+        T: Clone,
+        G: Clone,
+    {
+        self.copy_into(token, dest, dest_token, |_| true, false, G::clone)
+    }
+
+    // come clone_into ma il costo di ogni arco copiato è f applicata al costo originale, che può anche avere un tipo
+    // diverso (ad esempio per passare da distanze intere a probabilità). self non viene modificato
+    pub fn map_weights_into<'b, H>(
+        &self,
+        token: &GgToken<'id>,
+        dest: &GenerationalGraph<'b, T, H>,
+        dest_token: &mut GgToken<'b>,
+        f: impl Fn(&G) -> H,
+    ) -> HashMap<NodeId, NodeId>
+    where
+        T: Clone,
+    {
+        self.copy_into(token, dest, dest_token, |_| true, false, f)
+    }
+
+    // copia in dest solo i nodi il cui valore soddisfa keep e gli archi con entrambi gli estremi tra questi. I nodi copiati
+    // mantengono il loro ordine relativo di allocazione, ma in dest sono nodi nuovi con identità diverse: la mappa
+    // restituita associa ogni nodo tenuto di self alla sua copia e non contiene i nodi scartati
+    pub fn subgraph<'b>(
+        &self,
+        token: &GgToken<'id>,
+        dest: &GenerationalGraph<'b, T, G>,
+        dest_token: &mut GgToken<'b>,
+        keep: impl Fn(&T) -> bool,
+    ) -> HashMap<NodeId, NodeId>
+    where
+        T: Clone,
+        G: Clone,
+    {
+        self.copy_into(token, dest, dest_token, keep, false, G::clone)
+    }
+
+    // sposta in self tutti i nodi e gli archi di other, che viene consumato insieme alla sua arena: i valori vengono
+    // spostati e non copiati, quindi non servono T: Clone o G: Clone. I nodi vengono aggiunti nell'ordine di creazione
+    // di other (esclusi quelli rimossi con mark_removed) e gli archi vengono ricollegati ai nuovi nodi attraverso la
+    // loro posizione, compresi quelli paralleli. Gli archi tra other e altri network vengono rimossi, perché self
+    // potrebbe vivere più a lungo di quei network. La mappa restituita associa le identità dei nodi di other a quelle
+    // dei nuovi nodi di self: dopo la chiamata le prime non corrispondono più a nessun nodo
+    pub fn absorb<'b>(&self, other: GenerationalGraph<'b, T, G>, token: &mut GgToken<'id>) -> HashMap<NodeId, NodeId> {
+        let mut other = other;
+        let old_nodes = mem::take(other.index.get_mut());
+        let old_positions: HashMap<_, _> = old_nodes.iter().map(|&ptr| (ptr, unsafe { (*ptr).serial })).collect();
+        let mut edges = Vec::new();
+
+        for &ptr in old_nodes.iter() {
+            unsafe {
+                // il nodo viene distrutto insieme all'arena di other, quindi come in Drop va separato dagli altri network
+                (*ptr).release();
+
+                let source = (*ptr).serial;
+                let dead = mem::take((*ptr).dead_links.get_mut());
+                let links = mem::take(&mut (*ptr).links);
+                let parallel = mem::take(&mut (*ptr).parallel);
+                let parallel = parallel.into_iter().flat_map(|(next, costs)| costs.into_iter().map(move |cost| (next, cost)));
+
+                for (next, cost) in links.into_iter().chain(parallel) {
+                    if dead.contains(&next) {
+                        continue;
+                    }
+
+                    if let Some(&dest) = old_positions.get(&next) {
+                        edges.push((source, dest, cost));
+                    }
+                }
+            }
+        }
+
+        // other non ha più nodi nell'indice, quindi il suo Drop non tocca i nodi dell'arena che viene consumata qui
+        let arena = mem::replace(&mut other.nodes, Arena::new());
+        drop(other);
+
+        let mut copies = HashMap::new();
+
+        for node in arena.into_vec() {
+            let old = node.this;
+
+            if old_positions.contains_key(&old) {
+                copies.insert(node.serial, (old, self.add(node.value, token).ptr));
+            }
+        }
+
+        for (source, dest, cost) in edges {
+            let (source, dest) = (copies[&source].1, copies[&dest].1);
+            unsafe { (*source).push_link(dest, cost); }
+        }
+
+        copies.into_values().map(|(old, new)| (NodeId(old as usize), NodeId(new as usize))).collect()
+    }
+
+    // confronta gli archi di self con quelli di other usando mapping, che associa i nodi di self ai nodi corrispondenti
+    // di other (ad esempio la mappa restituita da clone_into). Restituisce gli archi aggiunti, cioè presenti in other ma
+    // non in self, come coppie di nodi di other, e quelli rimossi, presenti in self ma non in other, come coppie di nodi
+    // di self. Conta solo l'esistenza di un arco tra due nodi e non il suo costo; un arco con un estremo senza
+    // corrispondente viene sempre considerato aggiunto o rimosso. Gli archi verso altri network vengono ignorati
+    pub fn edge_diff<'b>(
+        &self,
+        other: &GenerationalGraph<'b, T, G>,
+        mapping: &HashMap<NodeId, NodeId>,
+    ) -> EdgeDiff {
+        let edges_of = |(nodes, positions): &Positions<T, G>| {
+            let mut edges = Vec::new();
+
+            for &ptr in nodes.iter() {
+                unsafe {
+                    for next in (*ptr).live_keys() {
+                        if positions.contains_key(next) {
+                            edges.push((NodeId(ptr as usize), NodeId(*next as usize)));
+                        }
+                    }
+                }
+            }
+
+            edges
+        };
+
+        let mine = edges_of(&self.positions());
+        let theirs = edges_of(&other.positions());
+
+        let translate = |(a, b): (NodeId, NodeId)| Some((*mapping.get(&a)?, *mapping.get(&b)?));
+        let translated: HashSet<_> = mine.iter().filter_map(|&edge| translate(edge)).collect();
+        let theirs_set: HashSet<_> = theirs.iter().copied().collect();
+
+        let added = theirs.into_iter().filter(|edge| !translated.contains(edge)).collect();
+        let removed = mine.into_iter().filter(|&edge| translate(edge).is_none_or(|e| !theirs_set.contains(&e))).collect();
+
+        (added, removed)
+    }
+
+    // copia in dest i nodi per cui keep è vero e gli archi tra di essi, eventualmente invertiti e con il costo
+    // convertito da cost. Gli indirizzi dei nodi copiati sono diversi da quelli originali, quindi gli archi vengono
+    // ricostruiti attraverso la tabella remap che associa ogni nodo originale alla sua copia. Valori e costi di self
+    // vengono letti, quindi serve anche il token di self
+    fn copy_into<'b, H>(
+        &self,
+        _token: &GgToken<'id>,
+        dest: &GenerationalGraph<'b, T, H>,
+        dest_token: &mut GgToken<'b>,
+        keep: impl Fn(&T) -> bool,
+        reversed: bool,
+        cost: impl Fn(&G) -> H,
+    ) -> HashMap<NodeId, NodeId>
+    where
+        T: Clone,
+    {
+        let (nodes, _) = self.positions();
+        let mut remap = HashMap::new();
+
+        for &ptr in nodes.iter() {
+            unsafe {
+                if keep(&(*ptr).value) {
+                    remap.insert(ptr, dest.add((*ptr).value.clone(), dest_token).ptr);
+                }
+            }
+        }
+
+        for &ptr in nodes.iter() {
+            let copy = match remap.get(&ptr) {
+                Some(&copy) => copy,
+                None => continue,
+            };
+
+            unsafe {
+                let parallel = (*ptr).live_parallel();
+
+                for (next, weight) in (*ptr).live_links().chain(parallel) {
+                    if let Some(&next_copy) = remap.get(next) {
+                        if reversed {
+                            (*next_copy).push_link(copy, cost(weight));
+                        } else {
+                            (*copy).push_link(next_copy, cost(weight));
+                        }
+                    }
+                }
+            }
+        }
+
+        remap.into_iter().map(|(ptr, copy)| (NodeId(ptr as usize), NodeId(copy as usize))).collect()
+    }
+
+    // pagerank di ogni nodo calcolato con iterations passi del metodo delle potenze. Il rank di un nodo viene diviso
+    // in parti uguali tra i suoi archi uscenti (i costi non vengono considerati), mentre quello dei nodi senza archi
+    // uscenti viene distribuito uniformemente su tutti i nodi. La somma dei rank è 1. Gli archi verso altri network
+    // vengono ignorati
+    pub fn pagerank(&self, damping: f64, iterations: usize) -> HashMap<NodeId, f64> {
+        let (nodes, positions) = self.positions();
+        let n = nodes.len();
+
+        if n == 0 {
+            return HashMap::new();
+        }
+
+        let targets: Vec<Vec<usize>> = nodes.iter()
+            .map(|&ptr| unsafe { (*ptr).live_keys().filter_map(|next| positions.get(next).copied()).collect() })
+            .collect();
+        let mut rank = vec![1.0 / n as f64; n];
+
+        for _ in 0..iterations {
+            let dangling: f64 = (0..n).filter(|&i| targets[i].is_empty()).map(|i| rank[i]).sum();
+            let mut next = vec![(1.0 - damping) / n as f64 + damping * dangling / n as f64; n];
+
+            for (i, out) in targets.iter().enumerate() {
+                for &j in out.iter() {
+                    next[j] += damping * rank[i] / out.len() as f64;
+                }
+            }
+
+            rank = next;
+        }
+
+        nodes.iter().zip(rank).map(|(&ptr, r)| (NodeId(ptr as usize), r)).collect()
+    }
+
+    // cammino (o circuito) euleriano che usa ogni arco esattamente una volta, archi paralleli e cappi compresi,
+    // con l'algoritmo di Hierholzer. Gli archi sono orientati: esiste solo se al massimo un nodo ha un arco uscente
+    // in più di quelli entranti (il nodo di partenza), al massimo uno ne ha uno entrante in più (quello di arrivo),
+    // tutti gli altri sono bilanciati e tutti gli archi sono raggiungibili dal nodo di partenza. Gli archi vengono
+    // consumati su una copia delle liste di adiacenza, il network non viene modificato. Un network senza archi ha un
+    // cammino vuoto, gli archi verso altri network vengono ignorati
+    pub fn eulerian_path(&self) -> Option<Vec<NodeId>> {
+        let (nodes, positions) = self.positions();
+        let n = nodes.len();
+        let mut adjacency = vec![Vec::new(); n];
+        let mut in_degree = vec![0usize; n];
+        let mut edges = 0;
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                let parallel = (*ptr).live_parallel().map(|(next, _)| next);
+
+                for next in (*ptr).live_keys().chain(parallel) {
+                    if let Some(&j) = positions.get(next) {
+                        adjacency[i].push(j);
+                        in_degree[j] += 1;
+                        edges += 1;
+                    }
+                }
+            }
+        }
+
+        if edges == 0 {
+            return Some(Vec::new());
+        }
+
+        let (mut start, mut end) = (None, None);
+
+        for i in 0..n {
+            let (out, inc) = (adjacency[i].len(), in_degree[i]);
+
+            if out == inc + 1 && start.is_none() {
+                start = Some(i);
+            } else if inc == out + 1 && end.is_none() {
+                end = Some(i);
+            } else if out != inc {
+                return None;
+            }
+        }
+
+        if start.is_some() != end.is_some() {
+            return None;
+        }
+
+        let start = start.unwrap_or_else(|| (0..n).find(|&i| !adjacency[i].is_empty()).unwrap());
+        let mut next = vec![0; n];
+        let mut stack = vec![start];
+        let mut path = Vec::with_capacity(edges + 1);
+
+        while let Some(&i) = stack.last() {
+            if next[i] < adjacency[i].len() {
+                stack.push(adjacency[i][next[i]]);
+                next[i] += 1;
+            } else {
+                path.push(NodeId(nodes[i] as usize));
+                stack.pop();
+            }
+        }
+
+        // se qualche arco non è raggiungibile da start il cammino costruito non li contiene tutti
+        if path.len() != edges + 1 {
+            return None;
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    // betweenness centrality di ogni nodo con l'algoritmo di Brandes: per ogni coppia di nodi (s, t) la frazione dei
+    // cammini minimi da s a t che passa per il nodo, sommata su tutte le coppie. Gli archi sono orientati e i cammini
+    // sono misurati in archi (i costi non vengono considerati); i valori non sono normalizzati. Viene eseguita una bfs
+    // da ogni nodo, seguita dalla propagazione all'indietro delle dipendenze, quindi il costo è O(V * (V + E))
+    pub fn betweenness_centrality(&self) -> HashMap<NodeId, f64> {
+        let (nodes, adjacency) = self.adjacency();
+        let n = nodes.len();
+        let mut centrality = vec![0.0; n];
+
+        for s in 0..n {
+            // order: nodi in ordine di distanza non decrescente da s, paths: numero di cammini minimi da s
+            let mut order = Vec::with_capacity(n);
+            let mut predecessors = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];
+            let mut distance = vec![usize::MAX; n];
+            let mut queue = VecDeque::from([s]);
+            paths[s] = 1.0;
+            distance[s] = 0;
+
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+
+                for &w in adjacency[v].iter() {
+                    if distance[w] == usize::MAX {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+
+                    if distance[w] == distance[v] + 1 {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.0; n];
+
+            while let Some(w) = order.pop() {
+                for &v in predecessors[w].iter() {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+
+                if w != s {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+
+        nodes.iter().zip(centrality).map(|(&ptr, c)| (NodeId(ptr as usize), c)).collect()
+    }
+
+    // closeness centrality di ogni nodo: il reciproco della somma delle distanze (in archi, seguendo la loro direzione)
+    // dal nodo a tutti i nodi che raggiunge. I nodi non raggiungibili vengono esclusi dalla somma invece di usare la
+    // variante armonica, quindi in un network non fortemente connesso un nodo che raggiunge pochi nodi vicini può avere
+    // un valore alto; un nodo che non raggiunge nessun altro nodo vale 0. Come betweenness_centrality costa O(V * (V + E))
+    pub fn closeness_centrality(&self) -> HashMap<NodeId, f64> {
+        let (nodes, adjacency) = self.adjacency();
+        let n = nodes.len();
+        let mut closeness = HashMap::with_capacity(n);
+
+        for s in 0..n {
+            let mut distance = vec![usize::MAX; n];
+            let mut queue = VecDeque::from([s]);
+            let mut total = 0;
+            distance[s] = 0;
+
+            while let Some(v) = queue.pop_front() {
+                total += distance[v];
+
+                for &w in adjacency[v].iter() {
+                    if distance[w] == usize::MAX {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                }
+            }
+
+            let value = if total == 0 { 0.0 } else { 1.0 / total as f64 };
+            closeness.insert(NodeId(nodes[s] as usize), value);
+        }
+
+        closeness
+    }
+
+    // nodi la cui rimozione aumenta il numero di componenti connesse, considerando gli archi come non orientati.
+    // Sono restituiti in ordine di allocazione
+    pub fn articulation_points(&self) -> Vec<NodeId> {
+        self.cut_points_and_bridges().0
+    }
+
+    // archi la cui rimozione aumenta il numero di componenti connesse, considerando gli archi come non orientati:
+    // a -> b e b -> a sono lo stesso arco, quindi una coppia collegata in entrambe le direzioni può comunque essere un ponte
+    pub fn bridges(&self) -> Vec<(NodeId, NodeId)> {
+        self.cut_points_and_bridges().1
+    }
+
+    // punti di articolazione e ponti calcolati con una sola visita in profondità, confrontando il tempo di scoperta di
+    // ogni nodo con il minimo tempo di scoperta raggiungibile dal suo sottoalbero (low). La visita usa uno stack
+    // esplicito di coppie (nodo, prossimo vicino da visitare) invece della ricorsione
+    fn cut_points_and_bridges(&self) -> (Vec<NodeId>, Vec<(NodeId, NodeId)>) {
+        let (nodes, adjacency) = self.undirected_adjacency();
+        let n = nodes.len();
+        let mut discovery = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut parent = vec![None; n];
+        let mut is_cut = vec![false; n];
+        let mut bridges = Vec::new();
+        let mut timer = 0;
+
+        for root in 0..n {
+            if discovery[root] != usize::MAX {
+                continue;
+            }
+
+            discovery[root] = timer;
+            low[root] = timer;
+            timer += 1;
+            let mut children = 0;
+            let mut stack = vec![(root, 0)];
+
+            while let Some((u, position)) = stack.last_mut() {
+                let u = *u;
+
+                if *position < adjacency[u].len() {
+                    let v = adjacency[u][*position];
+                    *position += 1;
+
+                    if v == u {
+                        continue;
+                    }
+
+                    if discovery[v] == usize::MAX {
+                        parent[v] = Some(u);
+                        discovery[v] = timer;
+                        low[v] = timer;
+                        timer += 1;
+
+                        if u == root {
+                            children += 1;
+                        }
+
+                        stack.push((v, 0));
+                    } else if parent[u] != Some(v) {
+                        low[u] = low[u].min(discovery[v]);
+                    }
+
+                    continue;
+                }
+
+                stack.pop();
+
+                if let Some(p) = parent[u] {
+                    low[p] = low[p].min(low[u]);
+
+                    if low[u] > discovery[p] {
+                        bridges.push((NodeId(nodes[p] as usize), NodeId(nodes[u] as usize)));
+                    }
+
+                    if p != root && low[u] >= discovery[p] {
+                        is_cut[p] = true;
+                    }
+                }
+            }
+
+            if children > 1 {
+                is_cut[root] = true;
+            }
+        }
+
+        let points = (0..n).filter(|&i| is_cut[i]).map(|i| NodeId(nodes[i] as usize)).collect();
+        (points, bridges)
+    }
+
+    // matrice di adiacenza del network: il vettore dei nodi fissa l'ordine (quello di allocazione) di righe e colonne e
+    // la cella (i, j) contiene il costo dell'arco dall'i-esimo al j-esimo nodo, None se non esiste. Per gli archi
+    // paralleli viene usato il primo, quelli verso altri network vengono ignorati. Occupa O(V^2) memoria anche per
+    // grafi sparsi, quindi va usata solo su grafi piccoli
+    pub fn to_adjacency_matrix(&self, _token: &GgToken<'id>) -> (Vec<NodeId>, Vec<Vec<Option<G>>>)
+    where
+        G: Clone,
+    {
+        let (nodes, positions) = self.positions();
+        let mut matrix = vec![vec![None; nodes.len()]; nodes.len()];
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for (next, cost) in (*ptr).live_links() {
+                    if let Some(&j) = positions.get(next) {
+                        matrix[i][j] = Some(cost.clone());
+                    }
+                }
+            }
+        }
+
+        (nodes.into_iter().map(|ptr| NodeId(ptr as usize)).collect(), matrix)
+    }
+
+    // rappresentazione del network in GraphML, leggibile da from_graphml e dagli strumenti esterni che supportano il
+    // formato. I nodi in ordine di allocazione ricevono gli id n0, n1, ... e ogni arco (compresi quelli paralleli) diventa
+    // un elemento edge diretto; value e cost convertono valori e costi in testo, salvato negli attributi "value" dei
+    // nodi e "cost" degli archi. Come in to_dot gli archi verso nodi di altri network vengono omessi
+    #[cfg(feature = "graphml")]
+    pub fn to_graphml(
+        &self,
+        _token: &GgToken<'id>,
+        value: impl Fn(&T) -> String,
+        cost: impl Fn(&G) -> String,
+    ) -> String {
+        let (nodes, positions) = self.positions();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        xml.push_str("  <key id=\"value\" for=\"node\" attr.name=\"value\" attr.type=\"string\"/>\n");
+        xml.push_str("  <key id=\"cost\" for=\"edge\" attr.name=\"cost\" attr.type=\"string\"/>\n");
+        xml.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                let label = xml_escape(&value(&(*ptr).value));
+                xml.push_str(&format!("    <node id=\"n{}\"><data key=\"value\">{}</data></node>\n", i, label));
+            }
+        }
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                let parallel = (*ptr).live_parallel();
+
+                for (next, edge_cost) in (*ptr).live_links().chain(parallel) {
+                    if let Some(&j) = positions.get(next) {
+                        xml.push_str(&format!(
+                            "    <edge source=\"n{}\" target=\"n{}\"><data key=\"cost\">{}</data></edge>\n",
+                            i,
+                            j,
+                            xml_escape(&cost(edge_cost))
+                        ));
+                    }
+                }
+            }
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    // rappresentazione del network nel formato dot di Graphviz. Ogni nodo è identificato dalla sua posizione in
+    // ordine di allocazione, label ed edge_label producono le etichette di nodi e archi. Gli archi paralleli vengono
+    // scritti tutti (dot li disegna come archi distinti), quelli verso nodi di altri network vengono omessi. Se G ha
+    // dimensione zero (ad esempio ()) il costo non porta informazione e gli archi vengono scritti senza etichetta
+    pub fn to_dot(
+        &self,
+        _token: &GgToken<'id>,
+        label: impl Fn(&T) -> String,
+        edge_label: impl Fn(&G) -> String,
+    ) -> String {
+        let (nodes, positions) = self.positions();
+        let mut dot = String::from("digraph {\n");
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                dot.push_str(&format!("    {} [label=\"{}\"];\n", i, dot_escape(&label(&(*ptr).value))));
+            }
+        }
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                for (next, cost) in (*ptr).live_links().chain((*ptr).live_parallel()) {
+                    if let Some(&j) = positions.get(next) {
+                        if mem::size_of::<G>() == 0 {
+                            dot.push_str(&format!("    {} -> {};\n", i, j));
+                        } else {
+                            dot.push_str(&format!("    {} -> {} [label=\"{}\"];\n", i, j, dot_escape(&edge_label(cost))));
+                        }
+                    }
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// stampa ogni nodo come "posizione: valore" seguito dai suoi archi uscenti come "posizione -> posizione (costo)",
+// usando la posizione in ordine di allocazione al posto dell'indirizzo. Gli archi paralleli vengono stampati tutti,
+// quelli verso altri network vengono omessi come in to_dot; come in to_dot i costi di dimensione zero non vengono stampati
+impl<T: fmt::Debug, G: fmt::Debug> fmt::Debug for GraphView<'_, '_, T, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (nodes, positions) = self.graph.positions();
+        writeln!(f, "GenerationalGraph {{")?;
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                writeln!(f, "    {}: {:?}", i, (*ptr).value)?;
+
+                let parallel = (*ptr).live_parallel();
+
+                for (next, cost) in (*ptr).live_links().chain(parallel) {
+                    if let Some(&j) = positions.get(next) {
+                        if mem::size_of::<G>() == 0 {
+                            writeln!(f, "        {} -> {}", i, j)?;
+                        } else {
+                            writeln!(f, "        {} -> {} ({:?})", i, j, cost)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "il network contiene un ciclo che passa per il nodo {:#x}", self.node.0)
+    }
+}
+
+impl error::Error for CycleError {}
+
+impl fmt::Display for NegativeCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "il nodo {:#x} è raggiungibile da un ciclo di costo negativo", self.node.0)
+    }
+}
+
+impl error::Error for NegativeCycleError {}
+
+impl fmt::Display for EdgeExistsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "esiste già un arco da {:#x} a {:#x}", self.from.0, self.to.0)
+    }
+}
+
+impl error::Error for EdgeExistsError {}
+
+impl fmt::Display for UnknownNodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "il nodo {:#x} non appartiene al network", self.node.0)
+    }
+}
+
+impl error::Error for UnknownNodeError {}
+
+#[cfg(feature = "graphml")]
+impl fmt::Display for GraphMlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "documento GraphML non valido: {}", self.reason)
+    }
+}
+
+#[cfg(feature = "graphml")]
+impl error::Error for GraphMlError {}
+
+// serializza il network come lista dei valori dei nodi più lista degli archi. Gli indirizzi dei nodi non sono
+// portabili quindi vengono sostituiti dalla posizione del nodo in ordine di allocazione. Gli archi verso nodi
+// di altri network non possono essere rappresentati e vengono omessi, quelli paralleli vengono serializzati tutti
+#[cfg(feature = "serde")]
+impl<T: Serialize, G: Serialize> Serialize for GraphView<'_, '_, T, G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let (nodes, positions) = self.graph.positions();
+        let mut values = Vec::with_capacity(nodes.len());
+        let mut edges = Vec::new();
+
+        for (i, &ptr) in nodes.iter().enumerate() {
+            unsafe {
+                values.push(&(*ptr).value);
+
+                let parallel = (*ptr).live_parallel();
+
+                for (next, cost) in (*ptr).live_links().chain(parallel) {
+                    if let Some(&j) = positions.get(next) {
+                        edges.push((i, j, cost));
+                    }
+                }
+            }
+        }
+
+        let mut state = serializer.serialize_struct("GenerationalGraph", 2)?;
+        state.serialize_field("nodes", &values)?;
+        state.serialize_field("edges", &edges)?;
+        state.end()
+    }
+}
+
+impl<'a, 'id, 'b, T, G> Deref for NodeRef<'a, 'id, 'b, T, G> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &(*self.ptr).value }
+    }
+}
+
+impl<'a, 'id, 'b, T, G> NodeRef<'a, 'id, 'b, T, G> {
+    pub fn id(&self) -> NodeId {
+        NodeId(self.ptr as usize)
+    }
+
+    // true se self e other si riferiscono allo stesso nodo, indipendentemente dal valore contenuto
+    pub fn same_node(&self, other: &NodeRef<'_, '_, '_, T, G>) -> bool {
+        self.ptr == other.ptr
+    }
+
+    // indice del nodo nell'ordine di creazione: il primo nodo creato con add ha indice 0, il successivo 1 e così via.
+    // Non dipende dai blocchi in cui l'arena alloca i nodi, quindi gli indici dei nodi di un network sono contigui e
+    // non cambiano per tutta la vita del network; un nodo rimosso con mark_removed mantiene il suo indice, che non viene
+    // riassegnato. A differenza di NodeId è un intero piccolo adatto come chiave esterna, ma è unico solo all'interno
+    // dello stesso network
+    pub fn index(&self) -> usize {
+        unsafe { (*self.ptr).serial }
+    }
+
+    // il nodo visto come NodeVisit, come lo riceverebbe la chiusura di visit. Viene restituito un riferimento in
+    // prestito da self e non un NodeVisit posseduto: NodeVisit non ha lifetime, quindi un valore posseduto potrebbe
+    // sopravvivere al network, mentre il riferimento non può sopravvivere al NodeRef e quindi al network. Dal NodeVisit
+    // sono raggiungibili anche gli altri nodi, quindi il riferimento è in prestito anche dal token
+    pub fn as_visit<'s>(&'s self, _token: &'s GgToken<'id>) -> &'s NodeVisit<T, G> {
+        NodeVisit::wrap(&self.ptr)
+    }
+
+    // valore del nodo in forma mutabile. Anche il token viene preso in prestito mutabile, così mentre il riferimento è
+    // vivo nessuna visita o iteratore sul network può leggere lo stesso valore
+    pub fn value_mut<'s>(&'s mut self, _token: &'s mut GgToken<'id>) -> &'s mut T {
+        unsafe { &mut (*self.ptr).value }
+    }
+
+    // arco da self a other con costo cost. Se l'arco esisteva già il costo viene sostituito e quello precedente
+    // viene ritornato, altrimenti viene ritornato None. Equivale a link_or_update
+    pub fn link(&mut self, other: &NodeRef<'a, 'id, '_, T, G>, cost: G, token: &mut GgToken<'id>) -> Option<G> {
+        self.link_or_update(other, cost, token)
+    }
+
+    // come link con costo G::default(), per network senza pesi (ad esempio con G = ()) in cui il costo non viene usato
+    pub fn link_unweighted(&mut self, other: &NodeRef<'a, 'id, '_, T, G>, token: &mut GgToken<'id>) -> Option<G>
+    where
+        G: Default,
+    {
+        self.link_or_update(other, G::default(), token)
+    }
+
+    // come link, ma il nome rende esplicito che un arco già esistente viene aggiornato. Gli archi sono indicizzati per
+    // destinazione, quindi viene sempre aggiornato il primo arco verso other: per archi paralleli serve link_multi
+    pub fn link_or_update(
+        &mut self,
+        other: &NodeRef<'a, 'id, '_, T, G>,
+        cost: G,
+        _token: &mut GgToken<'id>,
+    ) -> Option<G> {
+        unsafe { (*self.ptr).insert_link(other.ptr, cost) }
+    }
+
+    // arco temporaneo da self a other nello stesso network, rimosso quando il LinkHandle restituito viene distrutto,
+    // ad esempio per archi ipotetici durante una ricerca. Se esiste già un arco verso other il suo costo viene
+    // sostituito solo finché il LinkHandle è vivo: alla distruzione del LinkHandle l'arco torna al costo precedente
+    // invece di essere rimosso (gli eventuali archi paralleli non vengono toccati). Il LinkHandle tiene in prestito
+    // self e il token finché è vivo, e le visite sul network possono usare il token restituito da LinkHandle::token
+    #[must_use = "l'arco viene rimosso quando il LinkHandle viene distrutto"]
+    pub fn link_scoped<'h>(
+        &'h mut self,
+        other: &'h NodeRef<'a, 'id, '_, T, G>,
+        cost: G,
+        token: &'h mut GgToken<'id>,
+    ) -> LinkHandle<'h, 'id, T, G> {
+        let previous = unsafe { (*self.ptr).insert_link(other.ptr, cost) };
+
+        LinkHandle { source: self.ptr, dest: other.ptr, previous, token }
+    }
+
+    // arco da self a other solo se non ne esiste già uno: in quel caso il costo esistente non viene toccato e viene
+    // restituito un EdgeExistsError, così un builder incrementale non sovrascrive per errore un arco già creato
+    pub fn link_new(
+        &mut self,
+        other: &NodeRef<'a, 'id, '_, T, G>,
+        cost: G,
+        _token: &mut GgToken<'id>,
+    ) -> Result<(), EdgeExistsError> {
+        unsafe { (*self.ptr).revive(other.ptr); }
+
+        match unsafe { (*self.ptr).links.entry(other.ptr) } {
+            Entry::Occupied(_) => Err(EdgeExistsError { from: self.id(), to: other.id() }),
+            Entry::Vacant(entry) => {
+                entry.insert(cost);
+                Ok(())
+            }
+        }
+    }
+
+    // arco verso un nodo di un network esterno, cioè creato prima di quello di self e che quindi vive più a lungo.
+    // other deve avere lo stesso primo lifetime di self: essendo covariante, un nodo che vive più a lungo può essere
+    // usato al posto di uno che vive meno ma non il contrario, quindi collegare un nodo esterno a uno di un network
+    // interno con link_outer viene rifiutato dal borrow checker (il network interno non vive abbastanza). Se il
+    // network esterno viene comunque distrutto o svuotato con reset prima di quello di self l'arco diventa un arco
+    // morto, come per link_across
+    #[deprecated(note = "usare link_across, che restituisce un LinkHandle per rimuovere l'arco")]
+    pub fn link_outer(&mut self, other: &NodeRef<'a, '_, '_, T, G>, cost: G, _token: &mut GgToken<'id>) {
+        unsafe { (*self.ptr).insert_foreign(other.ptr, cost); }
+    }
+
+    // arco da self verso un nodo di un altro network ('other) che vive almeno quanto il NodeRef di self: di solito
+    // l'arco sparisce con il network di self mentre other è ancora valido. Il vincolo è nel primo lifetime: essendo
+    // covariante, il NodeRef di un network che vive più a lungo può essere accorciato fino a quello di self, mentre
+    // quello di un network che vive meno non può essere allungato. Quindi, con outer creato prima di inner:
+    //     inner_node.link_across(&outer_node, cost, &mut inner_token)   compila
+    //     outer_node.link_across(&inner_node, cost, &mut outer_token)   non compila (inner non vive abbastanza)
+    //     node.link_across(&other_node_of_same_graph, cost, &mut token) compila, come link
+    // Il LinkHandle restituito rimuove l'arco quando viene distrutto (o, come in link_scoped, riporta al costo
+    // precedente un arco che esisteva già), quindi va tenuto finché l'arco serve. Il vincolo vale solo finché esistono
+    // i NodeRef: se il LinkHandle non viene mai distrutto (mem::forget) l'arco resta, e il network di other può essere
+    // distrutto o svuotato con reset prima di quello di self (ad esempio se è stato spostato nella chiusura del network
+    // di self). In quel caso l'arco diventa un arco morto, ignorato da visite, iteratori e algoritmi, come quelli di
+    // link_inner verso un network interno distrutto
+    #[must_use = "l'arco viene rimosso quando il LinkHandle viene distrutto"]
+    pub fn link_across<'h, 'other>(
+        &'h mut self,
+        other: &'h NodeRef<'a, 'other, '_, T, G>,
+        cost: G,
+        token: &'h mut GgToken<'id>,
+    ) -> LinkHandle<'h, 'id, T, G> {
+        let previous = unsafe { (*self.ptr).insert_foreign(other.ptr, cost) };
+
+        LinkHandle { source: self.ptr, dest: other.ptr, previous, token }
+    }
+
+    // arco temporaneo verso un nodo di un network interno (che vive meno di self). L'arco esiste finché vive il
+    // LinkHandle restituito, che tiene in prestito other e quindi non può sopravvivere al network interno. Il token è
+    // quello del network di self, di cui il LinkHandle modifica gli archi. Anche se il LinkHandle non viene mai distrutto
+    // (mem::forget) l'arco diventa un arco morto, ignorato da visite e algoritmi, quando il network interno viene
+    // distrutto, quindi self non può seguire un puntatore a un nodo deallocato. Il contrario (un nodo interno che usa
+    // link_inner verso un nodo esterno) viene rifiutato dal borrow checker perché il terzo lifetime di other non può
+    // essere accorciato
+    pub fn link_inner<'h, 'c>(
+        &'h mut self,
+        other: &'h NodeRef<'c, '_, 'a, T, G>,
+        cost: G,
+        token: &'h mut GgToken<'id>,
+    ) -> LinkHandle<'h, 'id, T, G> {
+        let previous = unsafe { (*self.ptr).insert_foreign(other.ptr, cost) };
+
+        LinkHandle { source: self.ptr, dest: other.ptr, previous, token }
+    }
+
+    // archi uscenti dal nodo come coppie (destinazione, costo), senza passare da visit
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, &G)> {
+        unsafe {
+            let node = &*self.ptr;
+            node.links.iter().filter(move |(&dest, _)| !node.hides(dest)).map(|(&dest, cost)| (NodeId(dest as usize), cost))
+        }
+    }
+
+    // numero di archi uscenti dal nodo, un cappio conta come uno. Gli archi paralleli verso la stessa destinazione
+    // contano una volta sola, come negli algoritmi sul network
+    pub fn out_degree(&self) -> usize {
+        unsafe { (*self.ptr).live_count() }
+    }
+
+    // somma dei costi degli archi uscenti dal nodo, G::default() se non ce ne sono
+    pub fn total_out_cost(&self) -> G
+    where
+        G: Clone + Add<Output = G> + Default,
+    {
+        unsafe { (*self.ptr).live_edges().fold(G::default(), |total, (_, cost)| total + cost.clone()) }
+    }
+
+    // true se esiste un arco da self a other, anche se other appartiene a un altro network
+    pub fn is_linked_to(&self, other: &NodeRef<'_, '_, '_, T, G>) -> bool {
+        unsafe { (*self.ptr).link_to(other.ptr).is_some() }
+    }
+
+    // rimuove l'arco verso other e ne ritorna il costo, None se l'arco non esisteva. Vengono rimossi anche gli
+    // eventuali archi paralleli verso other, il costo ritornato è quello del primo
+    pub fn unlink(&mut self, other: &NodeRef<'_, '_, '_, T, G>, _token: &mut GgToken<'id>) -> Option<G> {
+        unsafe { (*self.ptr).remove_links(other.ptr) }
+    }
+
+    // aggiunge un arco da self a other anche se ne esiste già uno, così tra due nodi possono esserci più archi
+    // paralleli con costi diversi. Il primo arco verso other resta quello visto da link, set_weight e weight_of_ref,
+    // mentre gli algoritmi sul network li considerano tutti: i cammini minimi usano il più economico, longest_path il
+    // più costoso, max_flow somma le capacità e total_out_cost i costi. Solo to_adjacency_matrix usa il primo
+    pub fn link_multi(&mut self, other: &NodeRef<'a, 'id, '_, T, G>, cost: G, _token: &mut GgToken<'id>) {
+        unsafe { (*self.ptr).push_link(other.ptr, cost); }
+    }
+
+    // costi di tutti gli archi da self a other, a partire dal primo e seguiti dagli archi paralleli nell'ordine in
+    // cui sono stati aggiunti con link_multi. Vuoto se non esiste nessun arco
+    pub fn weights_to<'w>(&'w self, other: &NodeRef<'a, 'id, '_, T, G>) -> impl Iterator<Item = &'w G> {
+        unsafe {
+            (*self.ptr).costs_to(other.ptr)
+        }
+    }
+
+    // arco non orientato tra self e other. Non esiste un tipo di arco non orientato: vengono semplicemente inseriti
+    // i due archi orientati self -> other e other -> self con lo stesso costo. Vengono modificati gli archi di entrambi
+    // i nodi, quindi anche other è preso in prestito mutabile
+    pub fn link_undirected(&mut self, other: &mut NodeRef<'a, 'id, '_, T, G>, cost: G, _token: &mut GgToken<'id>)
+    where
+        G: Clone,
+    {
+        unsafe {
+            (*self.ptr).insert_link(other.ptr, cost.clone());
+            (*other.ptr).insert_link(self.ptr, cost);
+        }
+    }
+
+    // rimuove entrambi gli archi orientati inseriti da link_undirected
+    pub fn unlink_undirected(&mut self, other: &mut NodeRef<'a, 'id, '_, T, G>, _token: &mut GgToken<'id>) {
+        unsafe {
+            (*self.ptr).remove_links(other.ptr);
+            (*other.ptr).remove_links(self.ptr);
+        }
+    }
+
+    #[deprecated(note = "usare weight_of_ref, l'indirizzo passato come usize non viene controllato")]
+    pub fn weight_of<'w>(&'w self, dest: usize) -> Option<&'w G> {
+        unsafe {
+            (*self.ptr).link_to(dest as *mut Node<T, G>)
+        }
+    }
+
+    // peso dell'arco verso dest. A differenza di weight_of il nodo di destinazione è passato come NodeRef
+    // dello stesso network (stesso 'id), quindi non è possibile interrogare pesi con nodi di altri network:
+    //     GenerationalGraph::new(|g1, mut t1| {
+    //         let a = g1.add(1, &mut t1);
+    //         GenerationalGraph::new(|g2, mut t2| { a.weight_of_ref(&g2.add(2, &mut t2)); });
+    //     });
+    // non compila perché 'id è invariante e i due network hanno brand diversi. Lo stesso vale per set_weight e weights_to
+    pub fn weight_of_ref<'w>(&'w self, dest: &NodeRef<'a, 'id, '_, T, G>) -> Option<&'w G> {
+        unsafe {
+            (*self.ptr).link_to(dest.ptr)
+        }
+    }
+
+    // aggiorna il costo dell'arco verso other e ritorna il costo precedente. Se l'arco non esiste non viene creato
+    // e viene ritornato None. Come weight_of_ref other deve appartenere allo stesso network
+    pub fn set_weight(&mut self, other: &NodeRef<'a, 'id, '_, T, G>, cost: G, _token: &mut GgToken<'id>) -> Option<G> {
+        unsafe {
+            (*self.ptr).link_to_mut(other.ptr).map(|weight| mem::replace(weight, cost))
+        }
+    }
+
+    #[deprecated(note = "usare set_weight, l'indirizzo passato come usize non viene controllato")]
+    pub fn weight_of_mut<'w>(&'w mut self, dest: usize, _token: &'w mut GgToken<'id>) -> Option<&'w mut G> {
+        unsafe {
+            (*self.ptr).link_to_mut(dest as *mut Node<T, G>)
+        }
+    }
+
+    // cappio da self a self. Come per link al massimo un cappio per nodo: se esisteva già il costo viene sostituito e
+    // quello precedente viene ritornato. Un cappio conta come un solo arco uscente in out_degree ed edge_count
+    pub fn link_self(&mut self, cost: G, _token: &mut GgToken<'id>) -> Option<G> {
+        unsafe { (*self.ptr).insert_link(self.ptr, cost) }
+    }
+
+    // true se il nodo ha un cappio
+    pub fn has_self_loop(&self) -> bool {
+        unsafe { (*self.ptr).links.contains_key(&self.ptr) }
+    }
+
+    pub fn unlink_self(&mut self, _token: &mut GgToken<'id>) {
+        unsafe {
+            (*self.ptr).remove_links(self.ptr);
+        }
+    }
+}
+
+impl<'h, 'id, T, G> LinkHandle<'h, 'id, T, G> {
+    // token del network del nodo di partenza, preso in prestito dal handle: permette di usare visite e algoritmi sul
+    // network mentre l'arco temporaneo esiste, ad esempio per valutare un arco ipotetico creato con link_scoped
+    pub fn token(&self) -> &GgToken<'id> {
+        self.token
+    }
+
+    // costo attuale dell'arco temporaneo. Il nodo di partenza e la destinazione sono in prestito al handle, quindi
+    // l'arco esiste sempre finché il handle è vivo
+    pub fn cost(&self) -> &G {
+        unsafe { &(&(*self.source).links)[&self.dest] }
+    }
+
+    // aggiorna il costo dell'arco temporaneo e ritorna quello precedente
+    pub fn set_cost(&mut self, cost: G) -> Option<G> {
+        unsafe { (*self.source).links.get_mut(&self.dest).map(|weight| mem::replace(weight, cost)) }
+    }
+}
+
+impl<'id, T, G> EdgeTxn<'id, T, G> {
+    // come NodeRef::link: arco da source a dest, se esisteva già il costo viene sostituito e quello precedente ritornato.
+    // Se uno dei due nodi non appartiene al network non viene modificato nulla
+    pub fn link(&mut self, source: NodeId, dest: NodeId, cost: G) -> Result<Option<G>, UnknownNodeError>
+    where
+        G: Clone,
+    {
+        let source = GenerationalGraph::<'id, T, G>::resolve(&self.members, source)?;
+        let dest = GenerationalGraph::<'id, T, G>::resolve(&self.members, dest)?;
+
+        let previous = unsafe { (*source).insert_link(dest, cost) };
+        self.log.push((source, dest, previous.clone(), None));
+        Ok(previous)
+    }
+
+    // come NodeRef::unlink: rimuove gli archi da source a dest (anche quelli paralleli) e ritorna il costo del primo
+    pub fn unlink(&mut self, source: NodeId, dest: NodeId) -> Result<Option<G>, UnknownNodeError>
+    where
+        G: Clone,
+    {
+        let source = GenerationalGraph::<'id, T, G>::resolve(&self.members, source)?;
+        let dest = GenerationalGraph::<'id, T, G>::resolve(&self.members, dest)?;
+
+        unsafe { (*source).revive(dest); }
+        let parallel = unsafe { (*source).parallel.remove(&dest) };
+        let previous = unsafe { (*source).links.remove(&dest) };
+        self.log.push((source, dest, previous.clone(), parallel));
+        Ok(previous)
+    }
+}
+
+impl<T, G> Drop for LinkHandle<'_, '_, T, G> {
+    fn drop(&mut self) {
+        unsafe {
+            match self.previous.take() {
+                Some(cost) => {
+                    (*self.source).links.insert(self.dest, cost);
+                }
+                None => (*self.source).remove_first_link(self.dest),
+            }
+        }
+    }
+}
+
+// prima di liberare l'arena ogni nodo viene separato dagli altri network con Node::release (i nodi rimossi con
+// mark_removed lo sono già): gli archi da altri network verso i nodi di questo diventano archi morti e i nodi di questo
+// non risultano più tra i foreign_sources dei network ancora vivi. Non serve che i network esterni vivano più a lungo
+// di questo: i network si conoscono a vicenda solo finché esistono archi tra loro, quindi né l'ordine di distruzione
+// (anche se un network esterno è stato spostato nella chiusura di uno interno) né i LinkHandle mai distrutti con
+// mem::forget lasciano puntatori verso nodi deallocati
+impl<'id, T, G> Drop for GenerationalGraph<'id, T, G> {
+    fn drop(&mut self) {
+        for &ptr in self.index.get_mut().iter() {
+            unsafe { (*ptr).release(); }
+        }
+
+        #[cfg(debug_assertions)]
+        LIVE_GENERATIONS.lock().unwrap().retain(|&generation| generation != self.generation);
+    }
+}
+
+// il token non contiene dati: le letture dei valori di altri nodi prendono &GgToken e le modifiche &mut GgToken, ma
+// entrambe richiedono anche il network o un NodeRef, che non possono essere condivisi tra thread. Quindi condividere
+// &GgToken tra thread non permette nulla e spostarlo su un altro thread sposta solo il diritto di modificare il
+// network, che però resta sul thread che lo ha creato. Il network non è Sync: add prende &self e modifica arena e
+// indice (che usano celle non atomiche), quindi un altro thread con &GenerationalGraph potrebbe leggere l'indice
+// (iter_nodes, node_count, ...) mentre il thread con il token aggiunge un nodo. Per lo stesso motivo uno scope come
+//     thread::scope(|s| { s.spawn(|| graph.node_count()); graph.add(1, &mut token); })
+// non compila. Anche NodeRef non è né Send né Sync (contiene un puntatore): un &NodeRef su un altro thread potrebbe
+// leggere gli archi del nodo mentre questo thread li aggiorna senza passare dal NodeRef, ad esempio distruggendo un
+// network collegato, che segna come morti gli archi verso i suoi nodi. Le versioni par_* passano ai thread solo
+// indirizzi e li usano solo in lettura mentre il token è in prestito. Quindi uno scope come
+//     thread::scope(|s| { s.spawn(|| node.out_degree()); });
+// non compila, e il token non rende possibili modifiche concorrenti: serve &mut GgToken, che non può coesistere con
+// un &GgToken condiviso con un altro thread
+unsafe impl Send for GgToken<'_> {}
+unsafe impl Sync for GgToken<'_> {}
+
+fn main() {
+    GenerationalGraph::new(|graph1, mut token1| {
+        let mut x1 = graph1.add(1, &mut token1);
+        let mut x2 = graph1.add(1, &mut token1);
+        x1.link(&x2, 2, &mut token1);
+
+        graph1.visit(&x1, &token1, |root| {});
+
+        GenerationalGraph::new(|graph2, mut token2| {
+            let mut y1 = graph2.add(1, &mut token2);
+            let across = y1.link_across(&x1, 1, &mut token2);
+            drop(across);
+            let handle = x1.link_inner(&y1, 1, &mut token1);
+
+            // ...
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // un cammino più corto in archi (a -> b -> d) e uno più economico (a -> c -> d)
+    #[test]
+    fn shortest_path_prefers_cheaper_branch_of_diamond() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let mut c = graph.add("c", &mut token);
+            let d = graph.add("d", &mut token);
+            a.link(&b, 1, &mut token);
+            a.link(&c, 4, &mut token);
+            b.link(&d, 5, &mut token);
+            c.link(&d, 1, &mut token);
+
+            let (path, cost) = graph.shortest_path(&a, &d, &token).unwrap();
+            assert_eq!(path, vec![a.id(), c.id(), d.id()]);
+            assert_eq!(cost, 5);
+        });
+    }
+
+    #[test]
+    fn node_visit_equality_follows_the_node() {
+        GenerationalGraph::<i32, ()>::new(|graph, mut token| {
+            let a = graph.add(1, &mut token);
+            let b = graph.add(1, &mut token);
+
+            let mut seen = HashSet::new();
+            seen.insert(a.as_visit(&token));
+            seen.insert(a.as_visit(&token));
+            assert_eq!(seen.len(), 1);
+
+            seen.insert(b.as_visit(&token));
+            assert_eq!(seen.len(), 2);
+        });
+    }
+
+    #[test]
+    fn topological_sort_orders_dag() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut app = graph.add("app", &mut token);
+            let mut lib = graph.add("lib", &mut token);
+            let core = graph.add("core", &mut token);
+            app.link(&lib, (), &mut token);
+            app.link(&core, (), &mut token);
+            lib.link(&core, (), &mut token);
+
+            assert_eq!(graph.topological_sort(&app), Ok(vec![app.id(), lib.id(), core.id()]));
+        });
+    }
+
+    #[test]
+    fn topological_sort_reports_cycle() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&a, (), &mut token);
+
+            let error = graph.topological_sort(&a).unwrap_err();
+            assert!(error.node == a.id() || error.node == b.id());
+        });
+    }
+
+    #[test]
+    fn strongly_connected_components_split_cycle_and_isolated_node() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let c = graph.add("c", &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&a, (), &mut token);
+
+            let mut components = graph.strongly_connected_components();
+            components.iter_mut().for_each(|component| component.sort());
+            components.sort();
+
+            let mut cycle = vec![a.id(), b.id()];
+            cycle.sort();
+            let mut expected = vec![cycle, vec![c.id()]];
+            expected.sort();
+            assert_eq!(components, expected);
+        });
+    }
+
+    #[test]
+    fn all_pairs_shortest_paths_match_hand_computed_distances() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let mut c = graph.add("c", &mut token);
+            let mut d = graph.add("d", &mut token);
+            let e = graph.add("e", &mut token);
+            a.link(&b, 3, &mut token);
+            a.link(&c, 8, &mut token);
+            b.link(&c, 2, &mut token);
+            c.link(&d, 1, &mut token);
+            d.link(&a, 4, &mut token);
+
+            let dist = graph.all_pairs_shortest_paths(&token);
+            let (a, b, c, d, e) = (a.id(), b.id(), c.id(), d.id(), e.id());
+            assert_eq!(dist[&(a, c)], 5);
+            assert_eq!(dist[&(a, d)], 6);
+            assert_eq!(dist[&(b, a)], 7);
+            assert_eq!(dist[&(c, b)], 8);
+            assert_eq!(dist[&(d, c)], 9);
+            assert_eq!(dist[&(b, b)], 0);
+            // e non ha archi: raggiunge solo sé stesso e non è raggiungibile
+            assert!(!dist.contains_key(&(a, e)) && !dist.contains_key(&(e, a)));
+            assert_eq!(dist[&(e, e)], 0);
+        });
+    }
+
+    // griglia 3x3 con archi in entrambe le direzioni, da (0, 0) a (2, 0). La distanza di Manhattan è un'euristica
+    // ammissibile, quindi trova lo stesso costo dell'euristica nulla (che riduce A* a Dijkstra) raggiungendo meno nodi
+    #[test]
+    fn astar_finds_optimal_path_on_grid() {
+        GenerationalGraph::<(i32, i32), i32>::new(|graph, mut token| {
+            let mut cells: Vec<_> = (0..9).map(|i| graph.add((i % 3, i / 3), &mut token)).collect();
+
+            for i in 0..9 {
+                for j in 0..9 {
+                    let ((x1, y1), (x2, y2)) = (*cells[i], *cells[j]);
+                    if (x1 - x2).abs() + (y1 - y2).abs() == 1 {
+                        let (source, dest) = if i < j {
+                            let (left, right) = cells.split_at_mut(j);
+                            (&mut left[i], &right[0])
+                        } else {
+                            let (left, right) = cells.split_at_mut(i);
+                            (&mut right[0], &left[j])
+                        };
+                        source.link(dest, 1, &mut token);
+                    }
+                }
+            }
+
+            // l'euristica viene calcolata per ogni nodo inserito nella frontiera, quindi le chiamate contano i nodi
+            // raggiunti dalla ricerca
+            let search = |heuristic: fn(i32, i32) -> i32| {
+                let calls = cell::Cell::new(0);
+                let (path, cost) = graph
+                    .astar(&cells[0], &cells[2], &token, |node| {
+                        calls.set(calls.get() + 1);
+                        let (x, y) = *node.value();
+                        heuristic(x, y)
+                    })
+                    .unwrap();
+                (path, cost, calls.get())
+            };
+
+            let (path, cost, manhattan) = search(|x, y| (2 - x) + y);
+            let (_, zero_cost, zero) = search(|_, _| 0);
+
+            assert_eq!(cost, 2);
+            assert_eq!(path, vec![cells[0].id(), cells[1].id(), cells[2].id()]);
+            assert_eq!(zero_cost, cost);
+            assert_eq!(graph.shortest_path(&cells[0], &cells[2], &token).unwrap().1, cost);
+            assert!(manhattan < zero);
+        });
+    }
+
+    #[test]
+    fn clear_edges_keeps_nodes() {
+        GenerationalGraph::<i32, ()>::new(|mut graph, mut token| {
+            {
+                let mut a = graph.add(1, &mut token);
+                let mut b = graph.add(2, &mut token);
+                a.link(&b, (), &mut token);
+                b.link(&a, (), &mut token);
+                b.link_self((), &mut token);
+            }
+            assert_eq!(graph.edge_count(), 3);
+
+            graph.clear_edges(&mut token);
+            assert_eq!(graph.edge_count(), 0);
+            assert_eq!(graph.node_count(), 2);
+        });
+    }
+
+    // in un ciclo di tre nodi il rank si distribuisce in parti uguali
+    #[test]
+    fn pagerank_of_cycle_is_uniform() {
+        GenerationalGraph::<i32, ()>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let mut b = graph.add(2, &mut token);
+            let mut c = graph.add(3, &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&c, (), &mut token);
+            c.link(&a, (), &mut token);
+
+            let ranks = graph.pagerank(0.85, 50);
+            assert_eq!(ranks.len(), 3);
+            assert!(ranks.values().all(|rank| (rank - 1.0 / 3.0).abs() < 1e-9));
+        });
+    }
+
+    #[test]
+    fn find_path_returns_none_when_unreachable() {
+        GenerationalGraph::<i32, ()>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let mut b = graph.add(2, &mut token);
+            let c = graph.add(3, &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&a, (), &mut token);
+
+            assert_eq!(graph.find_path(&a, &b), Some(vec![a.id(), b.id()]));
+            assert_eq!(graph.find_path(&a, &c), None);
+        });
+    }
+
+    // due triangoli a-b-c e d-e-f uniti dall'arco c-d: c e d sono punti di articolazione e c-d è l'unico ponte
+    #[test]
+    fn barbell_has_one_bridge_and_two_cut_points() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut nodes: Vec<_> =
+                ["a", "b", "c", "d", "e", "f"].iter().map(|&name| graph.add(name, &mut token)).collect();
+
+            for (i, j) in [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3), (2, 3)] {
+                let (left, right) = nodes.split_at_mut(j.max(i));
+                let (first, second) = (&mut left[i.min(j)], &mut right[0]);
+                first.link_undirected(second, (), &mut token);
+            }
+
+            assert_eq!(graph.articulation_points(), vec![nodes[2].id(), nodes[3].id()]);
+
+            let bridges = graph.bridges();
+            assert_eq!(bridges.len(), 1);
+            let (from, to) = bridges[0];
+            assert!((from, to) == (nodes[2].id(), nodes[3].id()) || (from, to) == (nodes[3].id(), nodes[2].id()));
+        });
+    }
+
+    // s -> a (3), s -> b (2), a -> b (5), a -> t (2), b -> t (3): il taglio minimo è attorno a t e vale 5
+    #[test]
+    fn max_flow_on_classic_four_node_network() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut s = graph.add("s", &mut token);
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let t = graph.add("t", &mut token);
+            s.link(&a, 3, &mut token);
+            s.link(&b, 2, &mut token);
+            a.link(&b, 5, &mut token);
+            a.link(&t, 2, &mut token);
+            b.link(&t, 3, &mut token);
+
+            assert_eq!(graph.max_flow(&s, &t, &token), 5);
+            assert_eq!(graph.max_flow(&t, &s, &token), 0);
+        });
+    }
+
+    // un secondo add_or_get con lo stesso valore non alloca un nuovo nodo e restituisce l'identità del primo
+    #[test]
+    fn add_or_get_reuses_node_with_equal_value() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let a = match graph.add_or_get("a", &mut token) {
+                AddOrGet::Inserted(node) => node,
+                AddOrGet::Existing(_) => panic!("il network è vuoto"),
+            };
+
+            assert!(matches!(graph.add_or_get("a", &mut token), AddOrGet::Existing(id) if id == a.id()));
+            assert!(matches!(graph.add_or_get("b", &mut token), AddOrGet::Inserted(_)));
+            assert_eq!(graph.node_count(), 2);
+        });
+    }
+
+    // a -> b con costo 10 e un arco parallelo più economico: gli algoritmi devono vederli entrambi
+    #[test]
+    fn algorithms_consider_parallel_edges() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let b = graph.add("b", &mut token);
+            a.link(&b, 10, &mut token);
+            a.link_multi(&b, 1, &mut token);
+
+            assert_eq!(graph.shortest_path(&a, &b, &token), Some((vec![a.id(), b.id()], 1)));
+            assert_eq!(graph.shortest_path_edges(&a, &b, &token), Some(vec![(a.id(), b.id(), 1)]));
+            assert_eq!(graph.bellman_ford(&a, &token).unwrap()[&b.id()], 1);
+            assert_eq!(graph.all_pairs_shortest_paths(&token)[&(a.id(), b.id())], 1);
+            assert_eq!(graph.longest_path(&token), Some((vec![a.id(), b.id()], 10)));
+            assert_eq!(graph.max_flow(&a, &b, &token), 11);
+            assert_eq!(a.total_out_cost(), 11);
+        });
+    }
+
+    // circuito a -> b -> c -> a più il giro a -> d -> a
+    #[test]
+    fn eulerian_path_covers_every_edge_of_circuit() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let mut c = graph.add("c", &mut token);
+            let mut d = graph.add("d", &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&c, (), &mut token);
+            c.link(&a, (), &mut token);
+            a.link(&d, (), &mut token);
+            d.link(&a, (), &mut token);
+
+            let path = graph.eulerian_path().unwrap();
+            assert_eq!(path.len(), 6);
+            assert_eq!(path.first(), path.last());
+
+            let mut edges: Vec<_> = path.windows(2).map(|pair| (pair[0], pair[1])).collect();
+            edges.sort();
+            let mut expected =
+                vec![(a.id(), b.id()), (b.id(), c.id()), (c.id(), a.id()), (a.id(), d.id()), (d.id(), a.id())];
+            expected.sort();
+            assert_eq!(edges, expected);
+
+            // un arco in più rompe il bilanciamento dei gradi
+            b.link(&d, (), &mut token);
+            c.link(&d, (), &mut token);
+            assert_eq!(graph.eulerian_path(), None);
+        });
+    }
+
+    #[test]
+    fn clone_into_rebuilds_edges_between_copies() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            a.link(&b, 1, &mut token);
+            b.link(&a, 2, &mut token);
+            a.link_multi(&b, 3, &mut token);
+
+            GenerationalGraph::<&str, u32>::new(|copy, mut copy_token| {
+                let mapping = graph.clone_into(&token, &copy, &mut copy_token);
+                let (a2, b2) = (mapping[&a.id()], mapping[&b.id()]);
+                assert_ne!(a2, a.id());
+
+                let mut edges: Vec<_> =
+                    copy.iter_edges(&copy_token).map(|(from, to, &cost)| (from, to, cost)).collect();
+                edges.sort_by_key(|&(_, _, cost)| cost);
+                assert_eq!(edges, vec![(a2, b2, 1), (b2, a2, 2), (a2, b2, 3)]);
+
+                let values: Vec<_> = copy.iter_nodes(&copy_token).map(|node| *node.value()).collect();
+                assert_eq!(values, vec!["a", "b"]);
+            });
+        });
+    }
+
+    #[test]
+    fn self_loop_counts_once() {
+        GenerationalGraph::<i32, u32>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let b = graph.add(2, &mut token);
+            assert!(!a.has_self_loop());
+
+            assert_eq!(a.link_self(1, &mut token), None);
+            assert_eq!(a.link_self(2, &mut token), Some(1));
+            a.link(&b, 3, &mut token);
+
+            assert!(a.has_self_loop());
+            assert_eq!(a.out_degree(), 2);
+            assert_eq!(graph.edge_count(), 2);
+
+            a.unlink_self(&mut token);
+            assert!(!a.has_self_loop());
+            assert_eq!(a.out_degree(), 1);
+        });
+    }
+
+    // stella non orientata: tutti i cammini minimi tra due foglie passano dal centro
+    #[test]
+    fn betweenness_is_highest_at_star_center() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut center = graph.add("center", &mut token);
+            let mut leaves: Vec<_> = (0..4).map(|_| graph.add("leaf", &mut token)).collect();
+
+            for leaf in leaves.iter_mut() {
+                center.link_undirected(leaf, (), &mut token);
+            }
+
+            let centrality = graph.betweenness_centrality();
+            // 4 foglie, quindi 4 * 3 coppie ordinate di foglie diverse
+            assert_eq!(centrality[&center.id()], 12.0);
+            assert!(leaves.iter().all(|leaf| centrality[&leaf.id()] == 0.0));
+        });
+    }
+
+    // due cicli a <-> b e c <-> d collegati da b -> c, più il nodo isolato e
+    #[test]
+    fn condensation_collapses_cycles_into_a_dag() {
+        GenerationalGraph::<&str, ()>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let mut c = graph.add("c", &mut token);
+            let mut d = graph.add("d", &mut token);
+            let e = graph.add("e", &mut token);
+            a.link(&b, (), &mut token);
+            b.link(&a, (), &mut token);
+            c.link(&d, (), &mut token);
+            d.link(&c, (), &mut token);
+            b.link(&c, (), &mut token);
+            assert!(graph.has_cycle());
+
+            let components = graph.strongly_connected_components().len();
+            assert_eq!(components, 3);
+
+            GenerationalGraph::<Vec<NodeId>, ()>::new(|condensed, mut condensed_token| {
+                let mapping = graph.condensation(&condensed, &mut condensed_token);
+                assert_eq!(condensed.node_count(), components);
+                assert!(!condensed.has_cycle());
+                // resta solo l'arco tra la componente di b e quella di c
+                assert_eq!(condensed.edge_count(), 1);
+
+                assert_eq!(mapping[&a.id()], mapping[&b.id()]);
+                assert_eq!(mapping[&c.id()], mapping[&d.id()]);
+                assert_ne!(mapping[&b.id()], mapping[&c.id()]);
+                assert_ne!(mapping[&e.id()], mapping[&a.id()]);
+            });
+        });
+    }
+
+    #[test]
+    fn reachable_within_cost_excludes_nodes_over_budget() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a", &mut token);
+            let mut b = graph.add("b", &mut token);
+            let mut c = graph.add("c", &mut token);
+            let d = graph.add("d", &mut token);
+            a.link(&b, 2, &mut token);
+            b.link(&c, 2, &mut token);
+            c.link(&d, 5, &mut token);
+
+            let reachable = graph.reachable_within_cost(&a, 4, &token);
+            assert_eq!(reachable, [a.id(), b.id(), c.id()].into_iter().collect());
+        });
+    }
+
+    #[test]
+    fn absorb_moves_nodes_and_edges() {
+        GenerationalGraph::<&str, u32>::new(|graph, mut token| {
+            let existing = graph.add("existing", &mut token);
+
+            GenerationalGraph::<&str, u32>::new(|fragment, mut fragment_token| {
+                let (a, b) = {
+                    let mut a = fragment.add("a", &mut fragment_token);
+                    let mut b = fragment.add("b", &mut fragment_token);
+                    a.link(&b, 1, &mut fragment_token);
+                    b.link(&a, 2, &mut fragment_token);
+                    b.link_multi(&a, 3, &mut fragment_token);
+                    (a.id(), b.id())
+                };
+
+                let mapping = graph.absorb(fragment, &mut token);
+                let (a2, b2) = (mapping[&a], mapping[&b]);
+                assert_eq!(graph.node_count(), 3);
+
+                let mut edges: Vec<_> = graph.iter_edges(&token).map(|(from, to, &cost)| (from, to, cost)).collect();
+                edges.sort_by_key(|&(_, _, cost)| cost);
+                assert_eq!(edges, vec![(a2, b2, 1), (b2, a2, 2), (b2, a2, 3)]);
+                assert_ne!(a2, existing.id());
+            });
+        });
+    }
+
+    #[test]
+    fn retain_edges_prunes_weak_edges() {
+        GenerationalGraph::<&str, u32>::new(|mut graph, mut token| {
+            {
+                let mut a = graph.add("a", &mut token);
+                let mut b = graph.add("b", &mut token);
+                let c = graph.add("c", &mut token);
+                a.link(&b, 1, &mut token);
+                a.link(&c, 5, &mut token);
+                b.link(&c, 7, &mut token);
+                b.link_multi(&c, 2, &mut token);
+            }
+
+            assert_eq!(graph.retain_edges(|&cost| cost > 3, &mut token), 2);
+
+            let mut costs: Vec<_> = graph.iter_edges(&token).map(|(_, _, &cost)| cost).collect();
+            costs.sort();
+            assert_eq!(costs, vec![5, 7]);
+        });
+    }
+
+    // keep distrugge il network di destinazione di un arco del nodo che retain_edges sta esaminando: il nodo distrutto
+    // aggiunge quel nodo ai suoi dead_links mentre i verdetti vengono raccolti
+    #[test]
+    fn retain_edges_survives_keep_dropping_a_linked_network() {
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            GenerationalGraph::<i32, u32>::new(move |mut inner, mut inner_token| {
+                {
+                    let x = outer.add(1, &mut outer_token);
+                    let mut y = inner.add(2, &mut inner_token);
+                    let z = inner.add(3, &mut inner_token);
+                    mem::forget(y.link_across(&x, 5, &mut inner_token));
+                    y.link(&z, 1, &mut inner_token);
+                    y.link_multi(&z, 4, &mut inner_token);
+                }
+
+                let outer = cell::RefCell::new(Some(outer));
+                let keep = |&cost: &u32| {
+                    drop(outer.borrow_mut().take());
+                    cost > 1
+                };
+                assert_eq!(inner.retain_edges(keep, &mut inner_token), 1);
+                assert!(outer.borrow().is_none());
+
+                // resta solo l'arco parallelo verso z, promosso a primo arco; quello verso x è morto
+                let costs: Vec<_> = inner.iter_edges(&inner_token).map(|(_, _, &cost)| cost).collect();
+                assert_eq!(costs, vec![4]);
+                assert_eq!(inner.edge_count(), 1);
+            });
+        });
+    }
+
+    #[cfg(feature = "graphml")]
+    #[test]
+    fn graphml_round_trip_keeps_direction_and_costs() {
+        GenerationalGraph::<String, u32>::new(|graph, mut token| {
+            let mut a = graph.add("a & b".to_string(), &mut token);
+            let mut b = graph.add("<c>".to_string(), &mut token);
+            a.link(&b, 1, &mut token);
+            a.link_multi(&b, 4, &mut token);
+            b.link_self(2, &mut token);
+
+            let xml = graph.to_graphml(&token, |value| value.clone(), |cost| cost.to_string());
+
+            GenerationalGraph::<String, u32>::from_graphml(
+                &xml,
+                |text| Some(text.to_string()),
+                |text| text.parse().ok(),
+                |copy, copy_token| {
+                    let nodes: Vec<_> =
+                        copy.iter_nodes(&copy_token).map(|node| (node.id(), node.value().clone())).collect();
+                    assert_eq!(nodes.iter().map(|(_, value)| value.as_str()).collect::<Vec<_>>(), vec!["a & b", "<c>"]);
+
+                    let (a, b) = (nodes[0].0, nodes[1].0);
+                    let mut edges: Vec<_> =
+                        copy.iter_edges(&copy_token).map(|(from, to, &cost)| (from, to, cost)).collect();
+                    edges.sort_by_key(|&(_, _, cost)| cost);
+                    assert_eq!(edges, vec![(a, b, 1), (b, b, 2), (a, b, 4)]);
+                },
+            )
+            .unwrap();
+        });
+    }
+
+    // compila snippet come corpo di main in un crate che include questo file come modulo, così da verificare che il
+    // borrow checker rifiuti gli usi scorretti dell'API. Restituisce i messaggi di rustc se la compilazione fallisce e
+    // None se riesce. typed_arena viene preso dalla cartella con le dipendenze già compilate per i test
+    fn compile(name: &str, snippet: &str) -> Option<String> {
+        use std::{env, fs, path::Path, process::Command};
+
+        let deps = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        let arena = fs::read_dir(&deps)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| {
+                let file = path.file_name().unwrap().to_string_lossy();
+                file.starts_with("libtyped_arena-") && file.ends_with(".rlib")
+            })
+            .expect("typed_arena non compilato");
+
+        let dir = env::temp_dir().join(format!("gg-compile-fail-{}", name));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("case.rs");
+        let module = Path::new(env!("CARGO_MANIFEST_DIR")).join(file!());
+        fs::write(
+            &source,
+            format!(
+                "#[path = {:?}]\n#[allow(dead_code, unused, deprecated)]\nmod gg;\n#[allow(unused)]\nuse gg::*;\n\
+                 #[allow(unused, deprecated)]\nfn main() {{\n{}\n}}\n",
+                module, snippet
+            ),
+        )
+        .unwrap();
+
+        let output = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+            .args(["--edition", "2021", "--crate-type", "bin", "--emit", "metadata", "--cap-lints", "allow"])
+            .arg("-L")
+            .arg(&deps)
+            .arg("--extern")
+            .arg(format!("typed_arena={}", arena.display()))
+            .arg("--out-dir")
+            .arg(&dir)
+            .arg(&source)
+            .output()
+            .unwrap();
+
+        if output.status.success() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&output.stderr).into_owned())
+        }
+    }
+
+    // controlla che snippet venga rifiutato con l'errore code
+    fn assert_rejected(name: &str, snippet: &str, code: &str) {
+        match compile(name, snippet) {
+            Some(errors) => {
+                assert!(errors.contains(&format!("error[{}]", code)), "{} atteso, rustc ha detto:\n{}", code, errors)
+            }
+            None => panic!("{} compila ma dovrebbe essere rifiutato", name),
+        }
+    }
+
+    fn assert_accepted(name: &str, snippet: &str) {
+        if let Some(errors) = compile(name, snippet) {
+            panic!("{} dovrebbe compilare, rustc ha detto:\n{}", name, errors);
+        }
+    }
+
+    // un network esterno (outer, creato per primo) e uno interno creato nella sua chiusura, con CASE al posto del caso
+    const NESTED: &str = "
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            let mut x = outer.add(1, &mut outer_token);
+            GenerationalGraph::<i32, u32>::new(|inner, mut inner_token| {
+                let mut y = inner.add(2, &mut inner_token);
+                CASE
+            });
+        });";
+
+    fn nested(case: &str) -> String {
+        NESTED.replace("CASE", case)
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_outer_and_link_inner_accept_only_outward_pointers() {
+        assert_accepted(
+            "outward",
+            &nested("y.link_outer(&x, 1, &mut inner_token); drop(x.link_inner(&y, 1, &mut outer_token));"),
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_outer_rejects_shorter_lived_destination() {
+        assert_rejected("link_outer_inward", &nested("x.link_outer(&y, 1, &mut outer_token);"), "E0597");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_inner_rejects_longer_lived_destination() {
+        assert_rejected("link_inner_outward", &nested("drop(y.link_inner(&x, 1, &mut inner_token));"), "E0597");
+    }
+
+    // il LinkHandle di link_inner viene dimenticato e il network esterno (spostato nella chiusura di quello interno)
+    // viene distrutto per primo: il nodo interno non deve accorgersene
+    #[test]
+    fn forgotten_link_inner_survives_any_drop_order() {
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            GenerationalGraph::<i32, u32>::new(move |inner, mut inner_token| {
+                let y = inner.add(2, &mut inner_token);
+                {
+                    let mut x = outer.add(1, &mut outer_token);
+                    mem::forget(x.link_inner(&y, 5, &mut outer_token));
+                }
+                drop(outer);
+
+                inner.add(3, &mut inner_token);
+                assert_eq!(inner.node_count(), 2);
+            });
+        });
+
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            let mut x = outer.add(1, &mut outer_token);
+            GenerationalGraph::<i32, u32>::new(|inner, mut inner_token| {
+                let y = inner.add(2, &mut inner_token);
+                mem::forget(x.link_inner(&y, 1, &mut outer_token));
+            });
+
+            // l'arco verso il network interno distrutto è morto
+            assert_eq!(x.out_degree(), 0);
+            assert_eq!(x.edges().count(), 0);
+            assert_eq!(outer.edge_count(), 0);
+            assert_eq!(outer.iter_edges(&outer_token).count(), 0);
+        });
+    }
+
+    #[test]
+    fn mark_removed_kills_edges_from_other_networks() {
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            let mut x = outer.add(1, &mut outer_token);
+            GenerationalGraph::<i32, u32>::new(|mut inner, mut inner_token| {
+                let y = {
+                    let y = inner.add(2, &mut inner_token);
+                    mem::forget(x.link_inner(&y, 1, &mut outer_token));
+                    y.id()
+                };
+                assert_eq!(x.out_degree(), 1);
+
+                inner.mark_removed(y, &mut inner_token).unwrap();
+                assert_eq!(x.edges().count(), 0);
+            });
+            assert_eq!(x.out_degree(), 0);
+        });
+    }
+
+    // un solo network, con CASE al posto del caso
+    const SINGLE: &str = "
+        GenerationalGraph::<i32, u32>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let b = graph.add(2, &mut token);
+            CASE
+        });";
+
+    fn single(case: &str) -> String {
+        SINGLE.replace("CASE", case)
+    }
+
+    // il token può essere condiviso tra thread solo per leggere
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn token_can_be_shared_for_reading() {
+        assert_accepted(
+            "token_shared",
+            &single(
+                "let shared = &token; std::thread::scope(|s| { s.spawn(move || shared); s.spawn(move || shared); });",
+            ),
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn token_cannot_be_mutated_while_shared_with_a_thread() {
+        assert_rejected(
+            "token_shared_mutated",
+            &single(
+                "std::thread::scope(|s| { let shared = &token; s.spawn(move || shared); a.link(&b, 1, &mut token); });",
+            ),
+            "E0502",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn token_cannot_be_mutated_by_two_threads() {
+        assert_rejected(
+            "token_two_writers",
+            &single(concat!(
+                "std::thread::scope(|s| {",
+                "    s.spawn(|| { let _ = &mut token; });",
+                "    s.spawn(|| { let _ = &mut token; });",
+                "});",
+            )),
+            "E0499",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn node_ref_cannot_be_used_from_another_thread() {
+        assert_rejected(
+            "node_ref_thread",
+            &single("std::thread::scope(|s| { s.spawn(|| a.out_degree()); });"),
+            "E0277",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn graph_cannot_be_used_from_another_thread() {
+        assert_rejected(
+            "graph_thread",
+            &single("std::thread::scope(|s| { s.spawn(|| graph.node_count()); });"),
+            "E0277",
+        );
+    }
+
+    // neighbors converte una chiave della mappa degli archi alla volta in NodeVisit: eseguito anche sotto Miri per
+    // verificare che le conversioni (e visit e visit_multiple, che passano da as_visit) non producano UB
+    #[test]
+    fn neighbors_wrap_edge_keys_without_ub() {
+        GenerationalGraph::<i32, u32>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let mut b = graph.add(2, &mut token);
+            let c = graph.add(3, &mut token);
+            a.link(&b, 10, &mut token);
+            a.link(&c, 20, &mut token);
+            a.link_self(30, &mut token);
+            b.link(&c, 40, &mut token);
+
+            GenerationalGraph::<i32, u32>::new(|other, mut other_token| {
+                let foreign = other.add(4, &mut other_token);
+                let handle = a.link_inner(&foreign, 50, &mut token);
+                drop(handle);
+                mem::forget(a.link_inner(&foreign, 60, &mut token));
+            });
+
+            let mut values = graph.visit(&a, &token, |root| {
+                let mut costs = HashMap::new();
+                for (dest, cost) in root.neighbors() {
+                    // i NodeVisit ottenuti dalle chiavi si possono leggere, attraversare e usare come chiavi
+                    let expected = match *dest.value() {
+                        1 => 3,
+                        2 => 1,
+                        _ => 0,
+                    };
+                    assert_eq!(dest.neighbors().count(), expected);
+                    costs.insert(dest, *cost);
+                }
+                costs.iter().map(|(dest, &cost)| (*dest.value(), cost)).collect::<Vec<_>>()
+            });
+            values.sort();
+            assert_eq!(values, vec![(1, 30), (2, 10), (3, 20)]);
+
+            graph.visit_multiple(vec![&a, &b], &token, |roots| {
+                let mut values: Vec<_> =
+                    roots.iter().flat_map(|root| root.neighbor_values()).map(|(&v, &c)| (v, c)).collect();
+                values.sort();
+                assert_eq!(values, vec![(1, 30), (2, 10), (3, 20), (3, 40)]);
+                assert!(roots[0].is_linked_to(roots[1]));
+            });
+
+            graph.visit_mut(&mut a, &mut token, |root| *root.value_mut() += 100);
+            assert_eq!(*a, 101);
+        });
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn weight_of_ref_accepts_nodes_of_the_same_network() {
+        assert_accepted(
+            "weight_same",
+            &single("a.link(&b, 1, &mut token); assert_eq!(a.weight_of_ref(&b), Some(&1));"),
+        );
+    }
+
+    // i due network hanno brand diversi, quindi il nodo di un altro network non ha il tipo richiesto
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn weight_of_ref_rejects_nodes_of_another_network() {
+        assert_rejected("weight_nested", &nested("x.weight_of_ref(&y);"), "E0521");
+        assert_rejected(
+            "weight_sibling",
+            "GenerationalGraph::<i32, u32>::new(|first, mut first_token| {
+                 GenerationalGraph::<i32, u32>::new(|second, mut second_token| {
+                     let a = first.add(1, &mut first_token);
+                     let b = second.add(2, &mut second_token);
+                     a.weight_of_ref(&b);
+                 });
+             });",
+            "E0521",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_across_accepts_outer_and_same_network_destinations() {
+        assert_accepted(
+            "across_outward",
+            &nested("let handle = y.link_across(&x, 1, &mut inner_token); drop(handle);"),
+        );
+        assert_accepted("across_same", &single("let handle = a.link_across(&b, 1, &mut token); drop(handle);"));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_across_rejects_inner_destination() {
+        assert_rejected(
+            "across_inward",
+            &nested("let handle = x.link_across(&y, 1, &mut outer_token); drop(handle);"),
+            "E0597",
+        );
+    }
+
+    // il LinkHandle tiene in prestito la destinazione e il token finché è vivo
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_across_handle_borrows_destination_and_token() {
+        assert_rejected(
+            "across_moved_dest",
+            &single("let handle = a.link_across(&b, 1, &mut token); drop(b); handle.cost();"),
+            "E0505",
+        );
+        assert_rejected(
+            "across_token",
+            &single("let handle = a.link_across(&b, 1, &mut token); graph.add(3, &mut token); drop(handle);"),
+            "E0499",
+        );
+    }
+
+    #[test]
+    fn link_across_handle_removes_edge() {
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            let x = outer.add(1, &mut outer_token);
+            GenerationalGraph::<i32, u32>::new(|inner, mut inner_token| {
+                let mut y = inner.add(2, &mut inner_token);
+                {
+                    let handle = y.link_across(&x, 5, &mut inner_token);
+                    assert_eq!(*handle.cost(), 5);
+                }
+                assert_eq!(y.out_degree(), 0);
+            });
+        });
+    }
+
+    // il LinkHandle viene dimenticato e il network di destinazione viene distrutto o svuotato per primo: l'arco diventa
+    // un arco morto e sparisce da iteratori e statistiche
+    #[test]
+    fn forgotten_link_across_becomes_dead_edge() {
+        GenerationalGraph::<i32, u32>::new(|outer, mut outer_token| {
+            GenerationalGraph::<i32, u32>::new(move |inner, mut inner_token| {
+                {
+                    let x = outer.add(1, &mut outer_token);
+                    let mut y = inner.add(2, &mut inner_token);
+                    mem::forget(y.link_across(&x, 5, &mut inner_token));
+                    assert_eq!(y.out_degree(), 1);
+                }
+                drop(outer);
+
+                assert_eq!(inner.iter_edges(&inner_token).count(), 0);
+                assert_eq!(inner.edge_count(), 0);
+                assert_eq!(inner.stats().edge_count, 0);
+                assert!(inner.iter_nodes(&inner_token).all(|node| node.neighbors().count() == 0));
+
+                // i nuovi nodi possono riusare l'indirizzo del nodo distrutto senza ereditarne l'arco
+                for i in 0..50 {
+                    inner.add(i, &mut inner_token);
+                }
+                assert_eq!(inner.edge_count(), 0);
+            });
+        });
+
+        GenerationalGraph::<i32, u32>::new(|mut outer, mut outer_token| {
+            GenerationalGraph::<i32, u32>::new(move |inner, mut inner_token| {
+                {
+                    let x = outer.add(1, &mut outer_token);
+                    let mut y = inner.add(2, &mut inner_token);
+                    mem::forget(y.link_across(&x, 5, &mut inner_token));
+                }
+                outer.reset(&mut outer_token);
+                assert_eq!(inner.iter_edges(&inner_token).count(), 0);
+
+                let x = outer.add(7, &mut outer_token);
+                let mut y = inner.add(3, &mut inner_token);
+                drop(y.link_across(&x, 3, &mut inner_token));
+                assert_eq!(y.out_degree(), 0);
+            });
+        });
+    }
+
+    // un arco temporaneo sopra un arco esistente ne sostituisce il costo solo finché il LinkHandle è vivo
+    #[test]
+    fn link_scoped_restores_previous_cost() {
+        GenerationalGraph::<i32, u32>::new(|graph, mut token| {
+            let mut a = graph.add(1, &mut token);
+            let b = graph.add(2, &mut token);
+            a.link(&b, 7, &mut token);
+
+            {
+                let handle = a.link_scoped(&b, 3, &mut token);
+                assert_eq!(*handle.cost(), 3);
+            }
+            assert_eq!(a.weight_of_ref(&b), Some(&7));
+
+            a.unlink(&b, &mut token);
+            drop(a.link_scoped(&b, 3, &mut token));
+            assert_eq!(a.weight_of_ref(&b), None);
+        });
+    }
 }